@@ -0,0 +1,108 @@
+//! Rendering cost of typical frames through the ratatui backend
+//!
+//! Besides the CPU time measured by criterion, the size of each frame is reported along
+//! with the time the minitel takes to receive it at each speed.
+//! The example application is a binary crate, and is not part of the frames.
+
+use std::io::{Result, Write};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use minitel::{ratatui::MinitelBackend, stum::protocol::Baudrate};
+use ratatui::{
+    backend::Backend,
+    buffer::Buffer,
+    layout::Rect,
+    style::{Style, Stylize},
+    widgets::{Block, Widget},
+};
+
+/// Port counting the bytes sent, to simulate the transmission time
+#[derive(Default)]
+struct ThrottledPort {
+    sent: usize,
+}
+
+impl ThrottledPort {
+    /// Transmission time in milliseconds, with 10 bits per byte (start, 7 bits, parity, stop)
+    fn millis(&self, baudrate: Baudrate) -> f64 {
+        (self.sent * 10) as f64 * 1000.0 / baudrate.hertz() as f64
+    }
+}
+
+impl Write for ThrottledPort {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.sent += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn text_frame() -> Buffer {
+    let mut buffer = Buffer::empty(Rect::new(0, 0, 40, 25));
+    let line: String = ('a'..='z').chain('A'..='Z').cycle().take(40).collect();
+    for y in 0..25 {
+        buffer.set_string(0, y, &line, Style::default());
+    }
+    buffer
+}
+
+fn semigraphic_frame() -> Buffer {
+    let mut buffer = Buffer::empty(Rect::new(0, 0, 40, 25));
+    let sextants: String = ('\u{1FB00}'..='\u{1FB3B}').cycle().take(40).collect();
+    for y in 0..25 {
+        buffer.set_string(0, y, &sextants, Style::default().crossed_out());
+    }
+    buffer
+}
+
+fn block_frame() -> Buffer {
+    let area = Rect::new(0, 0, 40, 25);
+    let mut buffer = Buffer::empty(area);
+    Block::bordered()
+        .title(" Minitel ")
+        .render(area, &mut buffer);
+    buffer
+}
+
+fn draw(buffer: &Buffer) -> ThrottledPort {
+    let mut backend = MinitelBackend::new(ThrottledPort::default());
+    let content = buffer.content.iter().enumerate().map(|(i, cell)| {
+        let (x, y) = buffer.pos_of(i);
+        (x, y, cell)
+    });
+    backend.draw(content).unwrap();
+    backend.stream
+}
+
+fn frames(c: &mut Criterion) {
+    let frames = [
+        ("text", text_frame()),
+        ("semigraphic", semigraphic_frame()),
+        ("block", block_frame()),
+    ];
+    let mut group = c.benchmark_group("backend_frames");
+    for (name, buffer) in &frames {
+        let port = draw(buffer);
+        let speeds = [
+            Baudrate::B300,
+            Baudrate::B1200,
+            Baudrate::B4800,
+            Baudrate::B9600,
+        ];
+        let times: Vec<_> = speeds
+            .iter()
+            .map(|speed| format!("{:.0} ms at {speed}", port.millis(*speed)))
+            .collect();
+        println!("{name}: {} bytes, {}", port.sent, times.join(", "));
+
+        group.throughput(Throughput::Bytes(port.sent as u64));
+        group.bench_function(*name, |b| b.iter(|| black_box(draw(buffer))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, frames);
+criterion_main!(benches);
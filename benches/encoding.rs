@@ -0,0 +1,97 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use minitel::{
+    ratatui::MinitelBackend,
+    stum::videotex::{SIChar, StringMessage},
+    MinitelMessage,
+};
+use ratatui::{
+    backend::Backend,
+    buffer::Buffer,
+    layout::Rect,
+    style::{Style, Stylize},
+};
+
+/// A typical 40 columns line, with a few accentuated characters
+const FRENCH_LINE: &str = "Bienvenue sur le Minitel, à bientôt ! éè";
+
+fn string_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("string_encoding");
+    // Report both the time per char and the throughput of the input string
+    group.throughput(Throughput::Elements(FRENCH_LINE.chars().count() as u64));
+
+    group.bench_function("message", |b| {
+        b.iter(|| StringMessage(black_box(FRENCH_LINE).to_string()).message())
+    });
+
+    let message = StringMessage(FRENCH_LINE.to_string());
+    let mut buf = Vec::with_capacity(128);
+    group.bench_function("message_into", |b| {
+        b.iter(|| {
+            buf.clear();
+            black_box(&message).message_into(&mut buf);
+        })
+    });
+
+    let mut buf = Vec::with_capacity(128);
+    group.bench_function("encode_to", |b| {
+        b.iter(|| {
+            buf.clear();
+            for c in black_box(FRENCH_LINE).chars() {
+                if let Ok(c) = SIChar::try_from(c) {
+                    c.encode_to(&mut buf);
+                }
+            }
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("string_encoding_bytes");
+    group.throughput(Throughput::Bytes(FRENCH_LINE.len() as u64));
+    group.bench_with_input(
+        BenchmarkId::new("message_into", FRENCH_LINE.len()),
+        &message,
+        |b, message| {
+            let mut buf = Vec::with_capacity(128);
+            b.iter(|| {
+                buf.clear();
+                message.message_into(&mut buf);
+            })
+        },
+    );
+    group.finish();
+}
+
+/// Full screen with text on the top half and semi-graphics on the bottom half
+fn typical_screen() -> Buffer {
+    let area = Rect::new(0, 0, 40, 25);
+    let mut buffer = Buffer::empty(area);
+    for y in 0..12 {
+        buffer.set_string(0, y, FRENCH_LINE, Style::default());
+    }
+    let sextants: String = ('\u{1FB00}'..='\u{1FB3B}').cycle().take(40).collect();
+    for y in 12..25 {
+        buffer.set_string(0, y, &sextants, Style::default().crossed_out());
+    }
+    buffer
+}
+
+fn backend_draw(c: &mut Criterion) {
+    let buffer = typical_screen();
+    let mut group = c.benchmark_group("backend_draw");
+    group.throughput(Throughput::Elements(buffer.content.len() as u64));
+    group.bench_function("full_screen", |b| {
+        b.iter(|| {
+            let mut backend = MinitelBackend::new(Vec::with_capacity(4096));
+            let content = buffer.content.iter().enumerate().map(|(i, cell)| {
+                let (x, y) = buffer.pos_of(i);
+                (x, y, cell)
+            });
+            backend.draw(content).unwrap();
+            black_box(backend.stream)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, string_encoding, backend_draw);
+criterion_main!(benches);
@@ -4,7 +4,7 @@ use std::io;
 
 use minitel::{
     prelude::*,
-    ratatui::{widgets::Fill, MinitelBackend},
+    ratatui::{widgets::Fill, AsyncMinitelBackend},
     stum::videotex::{FunctionKey, StringMessage, UserInput, C0},
 };
 use ratatui::{
@@ -18,7 +18,6 @@ use ratatui::{
         Block, Padding, Paragraph, Tabs, Wrap,
     },
 };
-use std::io::Cursor;
 use strum::{Display, EnumIter, FromRepr, IntoEnumIterator};
 use time::{Date, Duration, Month};
 use tui_big_text::{BigText, PixelSize};
@@ -68,20 +67,11 @@ impl App {
         &mut self,
         minitel: &mut B,
     ) -> io::Result<()> {
-        // Prepare a write buffer for the sync->async bridge
-        let buffer: Vec<u8> = Vec::new();
-        let cursor: Cursor<Vec<u8>> = Cursor::new(buffer);
-        let backend = MinitelBackend::new(cursor);
-        let mut terminal = Terminal::new(backend)?;
+        let mut terminal = Terminal::new(AsyncMinitelBackend::buffered())?;
         while !self.exit {
-            // Draw the frame to the buffer
+            // Draw the frame to the buffer, then send it to the minitel
             terminal.draw(|frame| self.draw(frame))?;
-            // Flush the buffer to the minitel
-            let cursor = &mut terminal.backend_mut().stream;
-            let buffer = cursor.get_mut();
-            minitel.write(buffer).await?;
-            buffer.clear();
-            cursor.set_position(0);
+            terminal.backend_mut().flush_to(minitel).await?;
             // Read the minitel input
             self.handle_events(minitel).await?;
         }
@@ -255,20 +245,20 @@ impl App {
             .spacing(1)
             .margin(1)
             .areas(main_area);
-        let [l11, l12, l13] = Layout::vertical([
-            Constraint::Ratio(1, 3),
-            Constraint::Ratio(1, 3),
-            Constraint::Ratio(1, 3),
+        let [l11, l12, l13, l14] = Layout::vertical([
+            Constraint::Ratio(1, 4),
+            Constraint::Ratio(1, 4),
+            Constraint::Ratio(1, 4),
+            Constraint::Ratio(1, 4),
         ])
-        .spacing(1)
         .areas(h1);
 
-        let [l21, l22, l23] = Layout::vertical([
-            Constraint::Ratio(1, 3),
-            Constraint::Ratio(1, 3),
-            Constraint::Ratio(1, 3),
+        let [l21, l22, l23, l24] = Layout::vertical([
+            Constraint::Ratio(1, 4),
+            Constraint::Ratio(1, 4),
+            Constraint::Ratio(1, 4),
+            Constraint::Ratio(1, 4),
         ])
-        .spacing(1)
         .areas(h2);
 
         let mut border_style = Style::default();
@@ -320,6 +310,22 @@ impl App {
             border_style.set_style((Color::Black, Color::Green)),
         )
         .render(l23, buf);
+
+        border_demo(
+            " Sextant thin ",
+            "Sextants fins",
+            minitel::ratatui::border::SEXTANT_THIN,
+            border_style.set_style((Color::Black, Color::Green)),
+        )
+        .render(l14, buf);
+
+        border_demo(
+            " Sextant heavy ",
+            "Sextants épais",
+            minitel::ratatui::border::SEXTANT_HEAVY,
+            border_style.set_style((Color::Black, Color::Green)),
+        )
+        .render(l24, buf);
     }
 
     fn draw_instructions(&self, buf: &mut Buffer, instructions_area: Rect) {
@@ -441,3 +447,61 @@ pub const QUADRANT_OUTSIDE_TOP_FULL: border::Set = border::Set {
     horizontal_top: "█",
     horizontal_bottom: "▄",
 };
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    /// Port answering with pre-loaded inputs and recording what is sent
+    struct RecordingPort {
+        input: VecDeque<u8>,
+        output: Vec<u8>,
+    }
+
+    impl AsyncMinitelRead for RecordingPort {
+        async fn read(&mut self, data: &mut [u8]) -> io::Result<()> {
+            for byte in data.iter_mut() {
+                *byte = self
+                    .input
+                    .pop_front()
+                    .ok_or(io::Error::from(io::ErrorKind::UnexpectedEof))?;
+            }
+            Ok(())
+        }
+    }
+
+    impl AsyncMinitelWrite for RecordingPort {
+        async fn write(&mut self, data: &[u8]) -> io::Result<()> {
+            self.output.extend_from_slice(data);
+            Ok(())
+        }
+
+        async fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[tokio::test]
+    async fn browse_tabs() {
+        // Suite, Suite, Sommaire
+        let mut port = RecordingPort {
+            input: [0x13, 0x48, 0x13, 0x48, 0x13, 0x46].into(),
+            output: Vec::new(),
+        };
+        let mut app = App::default();
+        app.run(&mut port).await.unwrap();
+
+        assert!(matches!(app.selected_tab, SelectedTab::World));
+        assert!(port.input.is_empty());
+        // Home page, then calendar
+        assert!(contains(&port.output, b"Minitel A"));
+        assert!(contains(&port.output, b"Di Lu Ma Me Je Ve Sa"));
+        assert!(port.output.ends_with(b"\x0cAu revoir !"));
+    }
+}
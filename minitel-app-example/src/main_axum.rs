@@ -3,7 +3,7 @@
 //! It serves a websocket on /ws
 
 use axum::{
-    extract::ws::WebSocketUpgrade,
+    extract::{ws::WebSocketUpgrade, State},
     http::StatusCode,
     response::IntoResponse,
     routing::{any, post},
@@ -14,7 +14,12 @@ use clap::Parser;
 use serde::{Deserialize, Serialize};
 use tracing::{error, info, warn};
 
-use std::{collections::HashMap, net::SocketAddr, path::PathBuf};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 use tower_http::{
     services::ServeDir,
     trace::{DefaultMakeSpan, TraceLayer},
@@ -65,7 +70,8 @@ pub async fn main() {
         .route("/ws", any(ws_handler));
     if args.minipavi_host.is_some() {
         // minipavi api route
-        app = app.route("/minipavi", post(minipavi));
+        let sessions = Arc::new(Mutex::new(MinipaviSessionStore::new()));
+        app = app.route("/minipavi", post(minipavi).with_state(sessions));
     }
 
     // build our application with some routes
@@ -107,28 +113,40 @@ async fn ws_handler(
 }
 
 /// Minipavi entrypoint: Redirect to the websocket then exit
-async fn minipavi(Json(payload): Json<PasserelleMessage>) -> (StatusCode, Json<ServiceMessage>) {
+async fn minipavi(
+    State(sessions): State<Arc<Mutex<MinipaviSessionStore>>>,
+    Json(payload): Json<PasserelleMessage>,
+) -> (StatusCode, Json<ServiceMessage>) {
     let args = Args::parse();
+    let mut sessions = sessions.lock().unwrap();
+    let rep = handle_minipavi(&mut sessions, &args, payload.pavi);
+    (StatusCode::OK, rep.into())
+}
 
-    //let rep;
-    let rep = match payload.pavi.fctn.as_str() {
+/// Answer a minipavi call, keeping track of the session between the calls
+fn handle_minipavi(
+    sessions: &mut MinipaviSessionStore,
+    args: &Args,
+    payload: PaviMessage,
+) -> ServiceMessage {
+    match payload.fctn.as_str() {
         "DIRECTCNX" => {
             // Initial connection, redirect to the websocket
+            let session = MinipaviSession::from(&payload);
+            let context = session.context.clone();
+            sessions.insert(payload.unique_id, session);
+            let host = args.minipavi_host.clone().unwrap_or_default();
             ServiceMessage {
                 version: "1".to_string(),
                 content: base64::prelude::BASE64_STANDARD.encode(""),
-                context: "context".to_string(),
+                context,
                 echo: "on".to_string(),
-                next: format!(
-                    "{}://{}/minipavi",
-                    args.minipavi_proto,
-                    args.minipavi_host.as_ref().unwrap()
-                ),
+                next: format!("{}://{}/minipavi", args.minipavi_proto, host),
                 directcall: "no".to_string(),
                 command: Command {
                     name: "connectToWs".to_string(),
                     param: [
-                        ("host", args.minipavi_host.unwrap().as_str()),
+                        ("host", host.as_str()),
                         ("key", ""),
                         ("path", "/ws"),
                         ("echo", "on"),
@@ -143,39 +161,51 @@ async fn minipavi(Json(payload): Json<PasserelleMessage>) -> (StatusCode, Json<S
         }
         "DIRECTCALLENDED" | "FIN" => {
             // Call with the websocket ended, send the exit command
-            ServiceMessage {
-                version: "1".to_string(),
-                content: base64::prelude::BASE64_STANDARD.encode(""),
-                context: "context".to_string(),
-                echo: "off".to_string(),
-                next: "".to_string(),
-                directcall: "no".to_string(),
-                command: Command {
-                    name: "libCnx".to_string(),
-                    param: HashMap::new(),
-                },
-            }
+            let context = match sessions.remove(&payload.unique_id) {
+                Some(session) => {
+                    info!(
+                        "Minipavi session {} from {} (minitel {}) ended",
+                        session.unique_id, session.remote_addr, session.version_minitel
+                    );
+                    session.context
+                }
+                None => payload.context,
+            };
+            ServiceMessage::exit(context)
         }
         _ => {
             // Unknown function, send the exit command
-            error!("Unknown function {}", payload.pavi.fctn);
-            ServiceMessage {
-                version: "1".to_string(),
-                content: base64::prelude::BASE64_STANDARD.encode(""),
-                context: "context".to_string(),
-                echo: "off".to_string(),
-                next: "".to_string(),
-                directcall: "no".to_string(),
-                command: Command {
-                    name: "libCnx".to_string(),
-                    param: HashMap::new(),
-                },
-            }
+            error!("Unknown function {}", payload.fctn);
+            sessions.remove(&payload.unique_id);
+            ServiceMessage::exit(payload.context)
         }
-    };
-    (StatusCode::OK, rep.into())
+    }
+}
+
+/// State of a minipavi connection, kept between the calls
+#[derive(Debug, Clone)]
+struct MinipaviSession {
+    unique_id: String,
+    /// Service defined data, sent back and forth as is as the minipavi context
+    context: String,
+    remote_addr: String,
+    version_minitel: String,
+}
+
+impl From<&PaviMessage> for MinipaviSession {
+    fn from(message: &PaviMessage) -> Self {
+        Self {
+            unique_id: message.unique_id.clone(),
+            context: message.context.clone(),
+            remote_addr: message.remote_addr.clone(),
+            version_minitel: message.versionminitel.clone(),
+        }
+    }
 }
 
+/// Minipavi sessions, by unique id
+type MinipaviSessionStore = HashMap<String, MinipaviSession>;
+
 /// A message from the minipavi server to this service
 #[derive(Debug, Serialize, Deserialize)]
 struct PasserelleMessage {
@@ -210,6 +240,24 @@ struct ServiceMessage {
     command: Command,
 }
 
+impl ServiceMessage {
+    /// Exit command, ending the minitel connection
+    fn exit(context: String) -> Self {
+        ServiceMessage {
+            version: "1".to_string(),
+            content: base64::prelude::BASE64_STANDARD.encode(""),
+            context,
+            echo: "off".to_string(),
+            next: "".to_string(),
+            directcall: "no".to_string(),
+            command: Command {
+                name: "libCnx".to_string(),
+                param: HashMap::new(),
+            },
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Command {
     name: String,
@@ -0,0 +1,383 @@
+use std::{collections::HashMap, io::Result};
+
+use crate::{
+    stum::{
+        protocol::{RoutingRx, RoutingTx},
+        videotex::{
+            display_width, FunctionKey, Repeat, SIChar, SetPosition, StringMessage, UserInput, C0,
+        },
+    },
+    AsyncMinitelReadWrite, AsyncMinitelWrite, MinitelMessage,
+};
+
+/// What to do after handling a user input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppAction {
+    /// Keep the current display and wait for the next input
+    Continue,
+    /// Render the application again
+    Redraw,
+    /// Stop the application
+    Exit,
+}
+
+/// Minitel application driven by the user inputs, for services not using ratatui
+///
+/// The application only sees the user inputs and a writer, so the same implementation
+/// runs over every transport: serial port, TCP socket, or websocket. There is no separate
+/// blocking loop, blocking transports are wrapped in the async traits.
+///
+/// ```no_run
+/// use minitel::{
+///     app::{run_app, AppAction, MinitelApp},
+///     stum::videotex::{FunctionKey, UserInput, C0},
+///     AsyncMinitelReadWrite, AsyncMinitelWrite,
+/// };
+///
+/// struct Counter(u32);
+///
+/// impl MinitelApp for Counter {
+///     fn handle(&mut self, input: UserInput) -> AppAction {
+///         match input {
+///             UserInput::FunctionKey(FunctionKey::Suite) => {
+///                 self.0 += 1;
+///                 AppAction::Redraw
+///             }
+///             UserInput::FunctionKey(FunctionKey::ConnexionFin) => AppAction::Exit,
+///             _ => AppAction::Continue,
+///         }
+///     }
+///
+///     async fn render<W: AsyncMinitelWrite>(&mut self, minitel: &mut W) -> std::io::Result<()> {
+///         minitel.send(C0::FF).await?;
+///         minitel.write_str(&format!("Compteur: {}", self.0)).await
+///     }
+/// }
+///
+/// async fn serve(minitel: &mut impl AsyncMinitelReadWrite) -> std::io::Result<()> {
+///     run_app(&mut Counter(0), minitel).await
+/// }
+/// ```
+#[allow(async_fn_in_trait)]
+pub trait MinitelApp {
+    /// Update the application state from a user input
+    fn handle(&mut self, input: UserInput) -> AppAction;
+
+    /// Draw the application on the minitel
+    async fn render<W: AsyncMinitelWrite>(&mut self, minitel: &mut W) -> Result<()>;
+}
+
+/// Render the application, then feed it the user inputs until it exits
+pub async fn run_app<A, M>(app: &mut A, minitel: &mut M) -> Result<()>
+where
+    A: MinitelApp,
+    M: AsyncMinitelReadWrite,
+{
+    app.render(minitel).await?;
+    loop {
+        let input = minitel.read_s0_stroke().await?;
+        match app.handle(input) {
+            AppAction::Continue => {}
+            AppAction::Redraw => app.render(minitel).await?,
+            AppAction::Exit => return Ok(()),
+        }
+    }
+}
+
+/// Page of a [`Navigator`], writing its content in a byte buffer
+pub type Page = Box<dyn Fn(&mut Vec<u8>)>;
+
+/// Navigation requested by the user input handler of a [`Navigator`]
+pub enum Navigation {
+    /// Stay on the current page
+    Stay,
+    /// Open a new page on top of the current one
+    Push(Page),
+    /// Replace the current page, without keeping it in the history
+    Replace(Page),
+    /// Go back to the previous page
+    Pop,
+    /// Stop the navigation
+    Exit,
+}
+
+/// Stack of pages with history, following the usual minitel navigation model
+///
+/// Each page is rendered on a cleared screen. Retour goes back to the previous page.
+pub struct Navigator<'a, S> {
+    minitel: &'a mut S,
+    pages: Vec<Page>,
+}
+
+impl<'a, S: AsyncMinitelReadWrite> Navigator<'a, S> {
+    pub fn new(minitel: &'a mut S) -> Self {
+        Self {
+            minitel,
+            pages: Vec::new(),
+        }
+    }
+
+    /// Number of pages in the history, including the current one
+    pub fn depth(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Render a new page on top of the current one
+    pub async fn push(&mut self, page: Page) -> Result<()> {
+        self.pages.push(page);
+        self.render().await
+    }
+
+    /// Go back to the previous page and render it again
+    ///
+    /// The first page is never removed, returns `false` if there was nothing to go back to.
+    pub async fn pop(&mut self) -> Result<bool> {
+        if self.pages.len() <= 1 {
+            return Ok(false);
+        }
+        self.pages.pop();
+        self.render().await?;
+        Ok(true)
+    }
+
+    /// Render a new page in place of the current one
+    pub async fn replace(&mut self, page: Page) -> Result<()> {
+        self.pages.pop();
+        self.push(page).await
+    }
+
+    /// Feed the user inputs to the handler until it exits
+    ///
+    /// Retour is handled by the navigator and goes back to the previous page. The
+    /// handler receives the depth of the current page along with the input.
+    pub async fn run(
+        &mut self,
+        mut handler: impl FnMut(usize, UserInput) -> Navigation,
+    ) -> Result<()> {
+        loop {
+            let input = self.minitel.read_s0_stroke().await?;
+            if input == UserInput::FunctionKey(FunctionKey::Retour) {
+                self.pop().await?;
+                continue;
+            }
+            match handler(self.depth(), input) {
+                Navigation::Stay => {}
+                Navigation::Push(page) => self.push(page).await?,
+                Navigation::Replace(page) => self.replace(page).await?,
+                Navigation::Pop => {
+                    self.pop().await?;
+                }
+                Navigation::Exit => return Ok(()),
+            }
+        }
+    }
+
+    async fn render(&mut self) -> Result<()> {
+        let mut buf = vec![C0::FF.into()];
+        if let Some(page) = self.pages.last() {
+            page(&mut buf);
+        }
+        self.minitel.write(&buf).await
+    }
+}
+
+/// Characters accepted by a [`FormField`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    /// Any displayable character
+    Text,
+    /// Digits only
+    Numeric,
+    /// Any displayable character, echoed as `*`
+    Password,
+}
+
+/// Input field of a form, its label being followed by the input box
+#[derive(Debug, Clone, Copy)]
+pub struct FormField<'a> {
+    pub label: &'a str,
+    pub x: u8,
+    pub y: u8,
+    pub max_len: u8,
+    pub field_type: FieldType,
+}
+
+impl FormField<'_> {
+    /// Column of the first character of the input box
+    fn input_x(&self) -> u8 {
+        self.x + display_width(self.label) as u8 + 1
+    }
+
+    fn accepts(&self, c: char) -> bool {
+        match self.field_type {
+            FieldType::Numeric => c.is_ascii_digit(),
+            FieldType::Text | FieldType::Password => SIChar::try_from(c).is_ok(),
+        }
+    }
+}
+
+/// Draw the labels of a form, each followed by an input box made of dots
+pub async fn render_form<W: AsyncMinitelWrite>(
+    minitel: &mut W,
+    fields: &[FormField<'_>],
+) -> Result<()> {
+    let mut buf = Vec::new();
+    for field in fields {
+        buf.extend(SetPosition(field.x, field.y).message());
+        StringMessage(format!("{} ", field.label)).message_into(&mut buf);
+        if field.max_len > 0 {
+            buf.push(b'.');
+        }
+        if field.max_len > 1 {
+            buf.extend(Repeat(field.max_len - 1).message());
+        }
+    }
+    minitel.write(&buf).await
+}
+
+/// Let the user fill the fields of a form in order, returning the values by label
+///
+/// Envoi validates the current field, Correction removes its last character.
+/// The form echoes the accepted characters itself: the local echo is disabled while
+/// filling the form, and enabled again afterwards, even if filling the form fails.
+pub async fn fill_form<M: AsyncMinitelReadWrite>(
+    minitel: &mut M,
+    fields: &[FormField<'_>],
+) -> Result<HashMap<String, String>> {
+    minitel
+        .set_routing(false, RoutingRx::Screen, RoutingTx::Keyboard)
+        .await?;
+    let values = fill_fields(minitel, fields).await;
+    let restored = async {
+        minitel.send(C0::Coff).await?;
+        minitel
+            .set_routing(true, RoutingRx::Screen, RoutingTx::Keyboard)
+            .await
+    }
+    .await;
+    // The error of the form comes first, the restore likely failed for the same reason
+    let values = values?;
+    restored?;
+    Ok(values)
+}
+
+/// Read the fields of [`fill_form`], the local echo being disabled
+async fn fill_fields<M: AsyncMinitelReadWrite>(
+    minitel: &mut M,
+    fields: &[FormField<'_>],
+) -> Result<HashMap<String, String>> {
+    minitel.send(C0::Con).await?;
+    let mut values = HashMap::new();
+    for field in fields {
+        minitel.send(SetPosition(field.input_x(), field.y)).await?;
+        let mut value = String::new();
+        loop {
+            match minitel.read_s0_stroke().await? {
+                UserInput::FunctionKey(FunctionKey::Envoi) => break,
+                UserInput::FunctionKey(FunctionKey::Correction) if value.pop().is_some() => {
+                    minitel.write(&[C0::BS.into(), b'.', C0::BS.into()]).await?;
+                }
+                UserInput::Char(c)
+                    if field.accepts(c) && value.chars().count() < field.max_len as usize =>
+                {
+                    value.push(c);
+                    match field.field_type {
+                        FieldType::Password => minitel.write(b"*").await?,
+                        _ => minitel.write_str(c.encode_utf8(&mut [0; 4])).await?,
+                    }
+                }
+                _ => {}
+            }
+        }
+        values.insert(field.label.to_string(), value);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+#[cfg(feature = "futures")]
+mod tests {
+    use super::*;
+    use crate::tests::MockPort;
+
+    fn page(name: &'static [u8]) -> Page {
+        Box::new(move |buf| buf.extend_from_slice(name))
+    }
+
+    #[tokio::test]
+    async fn navigator_history() {
+        // Suite, Suite, Retour, Retour, Retour, Sommaire
+        let mut minitel = MockPort::new(&[
+            0x13, 0x48, 0x13, 0x48, 0x13, 0x42, 0x13, 0x42, 0x13, 0x42, 0x13, 0x46,
+        ]);
+        let mut navigator = Navigator::new(&mut minitel);
+        navigator.push(page(b"0")).await.unwrap();
+        navigator
+            .run(|depth, input| match input {
+                UserInput::FunctionKey(FunctionKey::Suite) => Navigation::Push(page(match depth {
+                    1 => b"1",
+                    _ => b"2",
+                })),
+                _ => Navigation::Exit,
+            })
+            .await
+            .unwrap();
+        assert_eq!(minitel.output, b"\x0c0\x0c1\x0c2\x0c1\x0c0");
+    }
+
+    const FIELDS: [FormField; 2] = [
+        FormField {
+            label: "Age",
+            x: 0,
+            y: 1,
+            max_len: 2,
+            field_type: FieldType::Numeric,
+        },
+        FormField {
+            label: "Code",
+            x: 0,
+            y: 2,
+            max_len: 4,
+            field_type: FieldType::Password,
+        },
+    ];
+
+    #[tokio::test]
+    async fn render_form() {
+        let mut minitel = MockPort::new(&[]);
+        super::render_form(&mut minitel, &FIELDS).await.unwrap();
+        assert_eq!(
+            minitel.output,
+            b"\x1f\x41\x41Age .\x12\x41\x1f\x42\x41Code .\x12\x43"
+        );
+    }
+
+    #[tokio::test]
+    async fn fill_form() {
+        let routing_ack = [0x1B, 0x3B, 0x63, 0x58, 0x00];
+        let mut input = routing_ack.to_vec();
+        // Age: letters are refused, 123 is truncated, corrected to 14
+        input.extend(b"a123\x13\x474\x13\x41");
+        // Code
+        input.extend(b"abc\x13\x41");
+        input.extend(routing_ack);
+        let mut minitel = MockPort::new(&input);
+
+        let values = super::fill_form(&mut minitel, &FIELDS).await.unwrap();
+        assert_eq!(values["Age"], "14");
+        assert_eq!(values["Code"], "abc");
+        let output = &minitel.output[5..minitel.output.len() - 5];
+        assert_eq!(output, b"\x11\x1f\x41\x4512\x08.\x084\x1f\x42\x46***\x14");
+        let enable_echo = minitel.output[minitel.output.len() - 5..].to_vec();
+
+        // Connection lost while filling the first field: the echo is still restored
+        let mut input = routing_ack.to_vec();
+        input.extend(b"1");
+        let mut minitel = MockPort::new(&input);
+        let err = super::fill_form(&mut minitel, &FIELDS).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        assert!(minitel
+            .output
+            .ends_with(&[&[0x14], &enable_echo[..]].concat()));
+    }
+}
@@ -2,9 +2,16 @@ use std::collections::VecDeque;
 use std::io::{ErrorKind, Result};
 
 use crate::{AsyncMinitelRead, AsyncMinitelWrite};
-use axum::extract::ws::WebSocket;
+use axum::extract::ws::{Message, WebSocket};
 
 /// A minitel port backed by an axum websocket
+///
+/// Once the client disconnects, reads fail with [`ErrorKind::ConnectionReset`].
+/// The websocket being served, the port can not reconnect by itself: the client
+/// opens a new websocket, handled by a new `Port`.
+///
+/// Reads are cancellation safe: the received bytes stay in the port buffer until
+/// a read can be fully served.
 pub struct Port {
     ws: WebSocket,
     buffer: VecDeque<u8>,
@@ -36,10 +43,19 @@ impl AsyncMinitelRead for Port {
     async fn read(&mut self, data: &mut [u8]) -> Result<()> {
         // The websocket provides data without control of the size
         // store them in a buffer, and deliver as much as requested
+        // Nothing is taken from the buffer before the loop ends, so that the read
+        // can be cancelled while waiting for a message without losing data
         while self.buffer.len() < data.len() {
-            let message = self.ws.recv().await.unwrap().unwrap();
-            if let axum::extract::ws::Message::Text(data) = message {
-                self.buffer.extend(data.as_bytes());
+            match self.ws.recv().await {
+                Some(Ok(Message::Text(data))) => self.buffer.extend(data.as_bytes()),
+                Some(Ok(Message::Close(_))) | None => {
+                    return Err(std::io::Error::new(
+                        ErrorKind::ConnectionReset,
+                        "Websocket closed",
+                    ))
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(axum_map_err(e)),
             }
         }
         for byte in data.iter_mut() {
@@ -50,5 +66,5 @@ impl AsyncMinitelRead for Port {
 }
 
 fn axum_map_err(e: axum::Error) -> std::io::Error {
-    std::io::Error::new(ErrorKind::Other, e.to_string())
+    std::io::Error::other(e.to_string())
 }
@@ -3,8 +3,11 @@ pub use esp::*;
 
 #[cfg(feature = "esp")]
 mod esp {
-    use crate::{AsyncMinitelBaudrateControl, AsyncMinitelRead, AsyncMinitelWrite};
+    use crate::{
+        AsyncMinitelRead, AsyncMinitelWrite, BaudrateControl, BlockingByteRead, ReadTimeout,
+    };
     use esp_idf_hal::{
+        delay::TickType,
         gpio::AnyIOPin,
         io::asynch::{Read, Write},
         sys::EspError,
@@ -14,6 +17,7 @@ mod esp {
     use std::{
         borrow::BorrowMut,
         io::{Error, ErrorKind, Result},
+        time::Duration,
     };
 
     /// Serial port configuration when the minitel starts
@@ -93,7 +97,7 @@ mod esp {
         }
     }
 
-    impl<'a, T> AsyncMinitelBaudrateControl for Port<'a, T>
+    impl<'a, T> BaudrateControl for Port<'a, T>
     where
         T: BorrowMut<uart::UartDriver<'a>>,
     {
@@ -104,7 +108,12 @@ mod esp {
                 .map_err(|e| Error::new(ErrorKind::Other, e))?;
             Ok(())
         }
+    }
 
+    impl<'a, T> BlockingByteRead for Port<'a, T>
+    where
+        T: BorrowMut<uart::UartDriver<'a>>,
+    {
         fn read_byte_blocking(&mut self) -> Result<u8> {
             let mut byte: [u8; 1] = [0];
             self.uart
@@ -115,6 +124,22 @@ mod esp {
             Ok(byte[0])
         }
     }
+
+    impl<'a, T> ReadTimeout for Port<'a, T>
+    where
+        T: BorrowMut<uart::UartDriver<'a>>,
+    {
+        fn read_byte_timeout(&mut self, timeout: Duration) -> Result<Option<u8>> {
+            let mut byte: [u8; 1] = [0];
+            let read = self
+                .uart
+                .driver()
+                .borrow_mut()
+                .read(&mut byte, TickType::from(timeout).ticks())
+                .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            Ok((read > 0).then_some(byte[0]))
+        }
+    }
 }
 
 /// Doc shenanigans: stubs for ESP32 integration documentation when the ESP toolchain is not available
@@ -122,8 +147,11 @@ mod esp {
 mod esp {
     use std::borrow::BorrowMut;
     use std::io::Result;
+    use std::time::Duration;
 
-    use crate::{AsyncMinitelBaudrateControl, AsyncMinitelRead, AsyncMinitelWrite};
+    use crate::{
+        AsyncMinitelRead, AsyncMinitelWrite, BaudrateControl, BlockingByteRead, ReadTimeout,
+    };
 
     #[doc(hidden)]
     pub mod uart {
@@ -191,16 +219,30 @@ mod esp {
         }
     }
 
-    impl<'a, T> AsyncMinitelBaudrateControl for Port<'a, T>
+    impl<'a, T> BaudrateControl for Port<'a, T>
     where
         T: BorrowMut<uart::UartDriver<'a>>,
     {
         fn set_baudrate(&mut self, _baudrate: crate::stum::protocol::Baudrate) -> Result<()> {
             unimplemented!()
         }
+    }
 
+    impl<'a, T> BlockingByteRead for Port<'a, T>
+    where
+        T: BorrowMut<uart::UartDriver<'a>>,
+    {
         fn read_byte_blocking(&mut self) -> Result<u8> {
             unimplemented!()
         }
     }
+
+    impl<'a, T> ReadTimeout for Port<'a, T>
+    where
+        T: BorrowMut<uart::UartDriver<'a>>,
+    {
+        fn read_byte_timeout(&mut self, _timeout: Duration) -> Result<Option<u8>> {
+            unimplemented!()
+        }
+    }
 }
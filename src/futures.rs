@@ -1,16 +1,59 @@
-use futures::{io::AsyncReadExt, io::AsyncWriteExt, TryFutureExt};
+use futures::{io::AsyncReadExt, io::AsyncWriteExt, Stream, TryFutureExt};
 use std::io::{Error, ErrorKind, Result};
 
-use crate::{AsyncMinitelRead, AsyncMinitelWrite};
+use crate::{
+    stum::videotex::{UserInput, C0},
+    AsyncMinitelRead, AsyncMinitelWrite,
+};
 
+/// Stream of the key strokes read from the minitel, assuming it is in S0 (text) mode
+///
+/// Errors are yielded as `Some(Err(...))`, the stream only ends when the underlying
+/// reader is exhausted.
+pub fn input_stream<R: AsyncMinitelRead>(
+    minitel: &mut R,
+) -> impl Stream<Item = Result<UserInput>> + '_ {
+    futures::stream::unfold(minitel, |minitel| async move {
+        match minitel.read_s0_stroke().await {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => None,
+            res => Some((res, minitel)),
+        }
+    })
+}
+
+/// Stream of the text lines typed on the minitel, assuming it is in S0 (text) mode
+///
+/// Lines end with LF, a preceding CR is dropped, as sent by telnet clients. The other
+/// key strokes are ignored. The last line is yielded even without LF at the end of the input.
+pub fn line_stream<R: AsyncMinitelRead>(
+    minitel: &mut R,
+) -> impl Stream<Item = Result<String>> + '_ {
+    futures::stream::unfold(minitel, |minitel| async move {
+        let mut line = String::new();
+        loop {
+            match minitel.read_s0_stroke().await {
+                Ok(UserInput::Char(c)) => line.push(c),
+                Ok(UserInput::C0(C0::LF)) => return Some((Ok(line), minitel)),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    return (!line.is_empty()).then_some((Ok(line), minitel))
+                }
+                Err(e) => return Some((Err(e), minitel)),
+            }
+        }
+    })
+}
+
+/// Minitel reads on any `futures` reader
+///
+/// Cancelling a read of more than one byte may lose the bytes already read.
 impl<T> AsyncMinitelRead for T
 where
     T: futures::io::AsyncRead + Unpin,
 {
     async fn read(&mut self, data: &mut [u8]) -> Result<()> {
-        self.read_exact(data)
-            .map_err(|e| Error::new(ErrorKind::Other, e))
-            .await?;
+        // Keep the error kind, UnexpectedEof marks the end of the input
+        self.read_exact(data).await?;
         Ok(())
     }
 }
@@ -20,16 +63,49 @@ where
     T: futures::io::AsyncWrite + Unpin,
 {
     async fn write(&mut self, data: &[u8]) -> Result<()> {
-        self.write_all(data)
-            .map_err(|e| Error::new(ErrorKind::Other, e))
-            .await?;
+        self.write_all(data).map_err(Error::other).await?;
         Ok(())
     }
 
     async fn flush(&mut self) -> Result<()> {
         futures::AsyncWriteExt::flush(self)
-            .map_err(|e| Error::new(ErrorKind::Other, e))
+            .map_err(Error::other)
             .await?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::{io::Cursor, StreamExt};
+
+    use super::*;
+    use crate::stum::videotex::FunctionKey;
+
+    #[tokio::test]
+    async fn input_stream_until_eof() {
+        let mut minitel = Cursor::new(vec![b'a', 0x13, 0x41, b'b']);
+        let inputs: Vec<_> = input_stream(&mut minitel)
+            .map(|input| input.unwrap())
+            .collect()
+            .await;
+        assert_eq!(
+            inputs,
+            vec![
+                UserInput::Char('a'),
+                UserInput::FunctionKey(FunctionKey::Envoi),
+                UserInput::Char('b'),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn line_stream_crlf() {
+        let mut minitel = Cursor::new(b"ab\r\n\ncd".to_vec());
+        let lines: Vec<_> = line_stream(&mut minitel)
+            .map(|line| line.unwrap())
+            .collect()
+            .await;
+        assert_eq!(lines, vec!["ab", "", "cd"]);
+    }
+}
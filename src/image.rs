@@ -0,0 +1,176 @@
+use std::io::{Error, ErrorKind, Result};
+
+use image::{imageops::FilterType, DynamicImage, Rgb};
+
+use crate::{
+    stum::videotex::{ColoredCanvas, C1},
+    AsyncMinitelWrite,
+};
+
+/// Colors of the minitel, in the order of the `C1` color attributes
+const PALETTE: [Rgb<u8>; 8] = [
+    Rgb([0, 0, 0]),
+    Rgb([255, 0, 0]),
+    Rgb([0, 255, 0]),
+    Rgb([255, 255, 0]),
+    Rgb([0, 0, 255]),
+    Rgb([255, 0, 255]),
+    Rgb([0, 255, 255]),
+    Rgb([255, 255, 255]),
+];
+
+/// Encoder of an image as colored semigraphic characters
+///
+/// The image is resized to 2×3 pixels per character, and each pixel is mapped to the
+/// closest minitel color in the CIELAB space. As a character only has two colors, the
+/// two most frequent colors of its pixels become its foreground and background.
+pub struct ImageEncoder {
+    pub image: DynamicImage,
+    target_cols: u8,
+    target_rows: u8,
+}
+
+impl ImageEncoder {
+    /// Encoder to a canvas of `target_cols`×`target_rows` characters
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if the canvas is empty or larger than the
+    /// 40×25 characters of the screen.
+    pub fn new(image: DynamicImage, target_cols: u8, target_rows: u8) -> Result<Self> {
+        if !(1..=40).contains(&target_cols) || !(1..=25).contains(&target_rows) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Image of {target_cols}×{target_rows} characters does not fit the screen"),
+            ));
+        }
+        Ok(Self {
+            image,
+            target_cols,
+            target_rows,
+        })
+    }
+
+    /// Encode the image as a canvas of `target_cols`×`target_rows` characters
+    pub fn encode(&self) -> ColoredCanvas {
+        let width = self.target_cols as u32 * 2;
+        let height = self.target_rows as u32 * 3;
+        let pixels = self
+            .image
+            .resize_exact(width, height, FilterType::Triangle)
+            .to_rgb8();
+        let palette = PALETTE.map(lab);
+
+        let mut canvas = ColoredCanvas::new(
+            self.target_cols,
+            self.target_rows,
+            C1::CharWhite,
+            C1::BgBlack,
+        );
+        for row in 0..self.target_rows {
+            for col in 0..self.target_cols {
+                let (x, y) = (col * 2, row * 3);
+                let mut colors = [0; 6];
+                let mut counts = [0; 8];
+                for (i, color) in colors.iter_mut().enumerate() {
+                    let (px, py) = (x + i as u8 % 2, y + i as u8 / 2);
+                    *color = closest(&palette, lab(*pixels.get_pixel(px as u32, py as u32)));
+                    counts[*color] += 1;
+                }
+                let mut by_count: Vec<usize> = (0..8).collect();
+                by_count.sort_by_key(|&c| std::cmp::Reverse(counts[c]));
+                let (bg, fg) = (by_count[0], by_count[1]);
+                for (i, &color) in colors.iter().enumerate() {
+                    let on = color != bg
+                        && (color == fg
+                            || distance(palette[color], palette[fg])
+                                < distance(palette[color], palette[bg]));
+                    canvas.set_pixel(x + i as u8 % 2, y + i as u8 / 2, on);
+                }
+                canvas.set_pixel_color(x, y, C1::from(0x40 + fg as u8), C1::from(0x50 + bg as u8));
+            }
+        }
+        canvas
+    }
+
+    /// Encode the image and draw it with its top left corner at (x, y)
+    pub async fn encode_to_minitel<W: AsyncMinitelWrite>(
+        &self,
+        minitel: &mut W,
+        x: u8,
+        y: u8,
+    ) -> Result<()> {
+        minitel.write_colored_canvas(x, y, &self.encode()).await
+    }
+}
+
+/// Index of the palette color closest to the CIELAB color
+fn closest(palette: &[[f32; 3]; 8], color: [f32; 3]) -> usize {
+    (0..palette.len())
+        .min_by(|&a, &b| distance(palette[a], color).total_cmp(&distance(palette[b], color)))
+        .unwrap_or_default()
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// sRGB to CIELAB, with a D65 white point
+fn lab(Rgb(rgb): Rgb<u8>) -> [f32; 3] {
+    let [r, g, b] = rgb.map(|c| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    let xyz = [
+        (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.9505,
+        0.2126 * r + 0.7152 * g + 0.0722 * b,
+        (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.089,
+    ];
+    let [fx, fy, fz] = xyz.map(|t| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    });
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbImage;
+
+    use super::*;
+    use crate::stum::videotex::G1;
+
+    #[test]
+    fn encode_two_colors() {
+        // Red top half, blue bottom third
+        let image = RgbImage::from_fn(2, 3, |_, y| match y {
+            0 | 1 => Rgb([250, 10, 10]),
+            _ => Rgb([10, 10, 240]),
+        });
+        let canvas = ImageEncoder::new(DynamicImage::ImageRgb8(image), 1, 1)
+            .unwrap()
+            .encode();
+        assert_eq!(
+            canvas.cells(),
+            &[vec![(G1::QUADRANT_LOWER_HALF, C1::CharBlue, C1::BgRed)]]
+        );
+    }
+
+    #[test]
+    fn encode_full_screen() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(8, 8));
+        let canvas = ImageEncoder::new(image.clone(), 40, 25).unwrap().encode();
+        assert_eq!(canvas.cells().len(), 25);
+        assert_eq!(canvas.cells()[0].len(), 40);
+
+        for (cols, rows) in [(0, 1), (41, 1), (200, 1), (1, 26), (1, 255)] {
+            let err = ImageEncoder::new(image.clone(), cols, rows).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        }
+    }
+}
@@ -3,8 +3,8 @@
 
 pub mod prelude {
     pub use crate::{
-        AsyncMinitelBaudrateControl, AsyncMinitelRead, AsyncMinitelReadWrite,
-        AsyncMinitelReadWriteBaudrate, AsyncMinitelWrite,
+        AsyncMinitelRead, AsyncMinitelReadWrite, AsyncMinitelReadWriteBaudrate, AsyncMinitelWrite,
+        BaudrateControl, BlockingByteRead, ReadTimeout,
     };
 }
 
@@ -13,6 +13,11 @@ pub mod prelude {
 /// The stum module (Spécifications Techniques d'Utilisation du Minitel) exposes parts of the STUM1B specification.
 pub mod stum;
 
+/// Application helpers
+///
+/// Building blocks for minitel services that do not use ratatui.
+pub mod app;
+
 /// Axum integration
 ///
 /// Implements the necessary traits to use a Minitel terminal over an Axum websocket.
@@ -29,6 +34,12 @@ pub mod futures;
 #[cfg(any(feature = "esp", feature = "espdoc"))]
 pub mod esp;
 
+/// Image encoding
+///
+/// Converts images to colored semigraphic characters.
+#[cfg(feature = "image")]
+pub mod image;
+
 /// Ratatui integration
 ///
 /// Exposes a backend for ratatui, a terminal UI library. This helps writing interactive
@@ -36,20 +47,67 @@ pub mod esp;
 #[cfg(feature = "ratatui")]
 pub mod ratatui;
 
-use std::io::{Error, ErrorKind, Result};
+use smallvec::SmallVec;
+use std::{
+    future::{poll_fn, Future},
+    io::{Error, ErrorKind, Result},
+    pin::pin,
+    task::Poll,
+    time::{Duration, Instant},
+};
 
 use stum::{
     protocol::{
-        Baudrate, FunctionMode, Pro1, Pro2, Pro2Resp, Pro3Resp, ProtocolMessage, Rom, RoutingRx,
-        RoutingTx,
+        Baudrate, FunctionMode, FunctionModeStatus, MinitelProtocolError, Pro1, Pro2, Pro2Resp,
+        Pro3Resp, ProtocolMessage, Rom, RoutingRx, RoutingStatus, RoutingTx,
+    },
+    videotex::{
+        display_width, ColorPair, ColoredCanvas, CsiSequence, FunctionKey, Repeat, SIChar,
+        SetPosition, StringMessage, UserInput, C0, C1, G0, G1, G2,
     },
-    videotex::{FunctionKey, UserInput, C0, C1, G0, G2},
 };
 
 pub trait MinitelMessage {
     fn message(self) -> Vec<u8>;
 }
 
+/// Raw bytes, sent as is
+impl MinitelMessage for &[u8] {
+    fn message(self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+impl MinitelMessage for Vec<u8> {
+    fn message(self) -> Vec<u8> {
+        self
+    }
+}
+
+impl<const N: usize> MinitelMessage for [u8; N] {
+    fn message(self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+impl<const N: usize> MinitelMessage for &[u8; N] {
+    fn message(self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+/// Read access to a minitel
+///
+/// # Cancellation safety
+///
+/// `read` should be cancellation safe: when its future is dropped before completion,
+/// for instance by a losing `tokio::select!` branch, no byte is consumed.
+/// The axum port guarantees it by keeping the received bytes in its buffer until the
+/// whole request can be served. The `futures` integration relies on `read_exact`, and
+/// only guarantees it for single byte reads.
+///
+/// The other methods chain several reads, and are not cancellation safe: a sequence
+/// interrupted half way, such as a function key, is lost.
 #[allow(async_fn_in_trait)]
 pub trait AsyncMinitelRead {
     async fn read(&mut self, data: &mut [u8]) -> Result<()>;
@@ -101,40 +159,154 @@ pub trait AsyncMinitelRead {
         }
     }
 
+    /// Skip the bytes until the expected one, reading at most [`DEFAULT_WAIT_RETRIES`] bytes
     #[inline(always)]
     async fn wait_for(&mut self, byte: impl Into<u8> + Copy) -> Result<()> {
-        for _ in 0..10 {
-            if self.read_byte().await? == byte.into() {
+        self.wait_for_n(byte, DEFAULT_WAIT_RETRIES).await
+    }
+
+    /// Skip the bytes until the expected one, reading at most `max_attempts` bytes
+    ///
+    /// Up to `max_attempts - 1` unrelated bytes are skipped before failing with
+    /// [`ErrorKind::TimedOut`], see [`AsyncMinitelRead::wait_for_step`].
+    async fn wait_for_n(&mut self, byte: impl Into<u8> + Copy, max_attempts: usize) -> Result<()> {
+        let byte = byte.into();
+        self.wait_for_step(byte, max_attempts, &format!("waiting for {byte:#04X}"))
+            .await
+    }
+
+    /// Skip the bytes until the expected one, as [`AsyncMinitelRead::wait_for_n`]
+    ///
+    /// The [`ErrorKind::TimedOut`] error wraps a [`MinitelProtocolError`] with the
+    /// last byte read, and `step` describing the exchange.
+    async fn wait_for_step(
+        &mut self,
+        byte: impl Into<u8> + Copy,
+        max_attempts: usize,
+        step: &str,
+    ) -> Result<()> {
+        let mut got = None;
+        for _ in 0..max_attempts {
+            let read = self.read_byte().await?;
+            if read == byte.into() {
                 return Ok(());
             }
+            got = Some(read);
+        }
+        match got {
+            Some(got) => Err(Error::new(
+                ErrorKind::TimedOut,
+                MinitelProtocolError::UnexpectedByte {
+                    step: step.to_string(),
+                    expected: byte.into(),
+                    got,
+                },
+            )),
+            None => Err(ErrorKind::TimedOut.into()),
         }
-        Err(ErrorKind::TimedOut.into())
     }
 
+    /// Read the next byte, failing with a [`MinitelProtocolError`] if it is not the expected one
     #[inline(always)]
     async fn expect_read(&mut self, byte: impl Into<u8> + Copy) -> Result<()> {
+        let byte = byte.into();
+        self.expect_read_step(byte, &format!("waiting for {byte:#04X}"))
+            .await
+    }
+
+    /// Read the next byte, failing with a [`MinitelProtocolError`] if it is not the expected one
+    ///
+    /// `step` describes the exchange in the error, such as "waiting for PRO2".
+    #[inline(always)]
+    async fn expect_read_step(&mut self, byte: impl Into<u8> + Copy, step: &str) -> Result<()> {
         let got = self.read_byte().await?;
         if got != byte.into() {
-            return Err(ErrorKind::InvalidData.into());
+            return Err(MinitelProtocolError::UnexpectedByte {
+                step: step.to_string(),
+                expected: byte.into(),
+                got,
+            }
+            .into());
         }
         Ok(())
     }
 
     #[inline(always)]
     async fn read_pro2(&mut self, expected_ack: Pro2Resp) -> Result<u8> {
-        self.wait_for(C0::ESC).await?;
-        self.expect_read(C1::Pro2).await?;
-        self.expect_read(expected_ack).await?;
+        self.wait_for_step(C0::ESC, DEFAULT_WAIT_RETRIES, "waiting for ESC")
+            .await?;
+        self.expect_read_step(C1::Pro2, "waiting for PRO2").await?;
+        self.expect_read_step(expected_ack, &format!("waiting for {expected_ack:?}"))
+            .await?;
         self.read_byte().await
     }
 
     #[inline(always)]
     async fn read_pro3(&mut self, expected_ack: Pro3Resp) -> Result<(u8, u8)> {
-        self.wait_for(C0::ESC).await?;
-        self.expect_read(C1::Pro3).await?;
-        self.expect_read(expected_ack).await?;
+        self.wait_for_step(C0::ESC, DEFAULT_WAIT_RETRIES, "waiting for ESC")
+            .await?;
+        self.expect_read_step(C1::Pro3, "waiting for PRO3").await?;
+        self.expect_read_step(expected_ack, &format!("waiting for {expected_ack:?}"))
+            .await?;
         Ok((self.read_byte().await?, self.read_byte().await?))
     }
+
+    /// Read key strokes in S0 (text) mode until the predicate matches, including the matching stroke
+    async fn read_s0_strokes_until(
+        &mut self,
+        predicate: impl Fn(&UserInput) -> bool,
+    ) -> Result<Vec<UserInput>> {
+        let mut strokes = Vec::new();
+        loop {
+            let stroke = self.read_s0_stroke().await?;
+            let done = predicate(&stroke);
+            strokes.push(stroke);
+            if done {
+                return Ok(strokes);
+            }
+        }
+    }
+
+    /// Read a text input until the user presses Envoi
+    ///
+    /// Correction removes the last character. Characters beyond `max_len` are ignored,
+    /// as well as the other key strokes. The minitel local echo is left as is.
+    async fn read_text_input(&mut self, max_len: usize) -> Result<String> {
+        let mut text = String::new();
+        loop {
+            match self.read_s0_stroke().await? {
+                UserInput::FunctionKey(FunctionKey::Envoi) => return Ok(text),
+                UserInput::FunctionKey(FunctionKey::Correction) => {
+                    text.pop();
+                }
+                UserInput::Char(c) if text.chars().count() < max_len => text.push(c),
+                _ => {}
+            }
+        }
+    }
+
+    /// Read a line until CR or Envoi, as typed on a keyboard or sent by a terminal emulator
+    ///
+    /// BS and Correction remove the last character, characters beyond `max_len` are
+    /// ignored. Connexion/Fin fails with [`ErrorKind::ConnectionAborted`].
+    async fn read_line(&mut self, max_len: usize) -> Result<String> {
+        let mut line = String::new();
+        loop {
+            match self.read_s0_stroke().await? {
+                UserInput::C0(C0::CR) | UserInput::FunctionKey(FunctionKey::Envoi) => {
+                    return Ok(line)
+                }
+                UserInput::FunctionKey(FunctionKey::ConnexionFin) => {
+                    return Err(ErrorKind::ConnectionAborted.into())
+                }
+                UserInput::C0(C0::BS) | UserInput::FunctionKey(FunctionKey::Correction) => {
+                    line.pop();
+                }
+                UserInput::Char(c) if line.chars().count() < max_len => line.push(c),
+                _ => {}
+            }
+        }
+    }
 }
 
 #[allow(async_fn_in_trait)]
@@ -145,158 +317,1254 @@ pub trait AsyncMinitelWrite {
     async fn send(&mut self, message: impl MinitelMessage) -> Result<()> {
         self.write(&message.message()).await
     }
-}
 
-/// Ability to change the baudrate of the serial port
-#[allow(async_fn_in_trait)]
-pub trait AsyncMinitelBaudrateControl {
-    /// Change the baudrate of the serial port
-    fn set_baudrate(&mut self, baudrate: Baudrate) -> Result<()>;
+    /// Write a string, skipping the characters that can not be displayed
+    ///
+    /// The string is encoded on the stack, and written by chunks.
+    async fn write_str(&mut self, s: &str) -> Result<()> {
+        let mut buf: SmallVec<[u8; 64]> = SmallVec::new();
+        for c in s.chars().flat_map(SIChar::try_from) {
+            // An encoded character takes up to 3 bytes
+            if buf.len() > buf.inline_size() - 3 {
+                self.write(&buf).await?;
+                buf.clear();
+            }
+            c.encode_to(&mut buf);
+        }
+        if !buf.is_empty() {
+            self.write(&buf).await?;
+        }
+        Ok(())
+    }
 
-    /// Read, non async
-    fn read_byte_blocking(&mut self) -> Result<u8>;
-}
+    /// Write a number in decimal, without allocation
+    async fn write_uint(&mut self, n: u32) -> Result<()> {
+        let mut digits = [0; 10];
+        self.write(encode_decimal(n, &mut digits)).await
+    }
 
-#[allow(async_fn_in_trait)]
-pub trait AsyncMinitelReadWrite: AsyncMinitelRead + AsyncMinitelWrite {
+    /// Write a signed number in decimal, without allocation
+    async fn write_int(&mut self, n: i32) -> Result<()> {
+        let mut digits = [0; 11];
+        let start = digits.len() - encode_decimal(n.unsigned_abs(), &mut digits[1..]).len();
+        if n < 0 {
+            digits[start - 1] = b'-';
+            self.write(&digits[start - 1..]).await
+        } else {
+            self.write(&digits[start..]).await
+        }
+    }
+
+    /// Write a string, replacing the characters that can not be displayed by `?`
+    ///
+    /// Returns the number of replaced characters.
     #[inline(always)]
-    async fn read_rom(&mut self) -> Result<Rom> {
-        self.send(ProtocolMessage::Pro1(Pro1::EnqRom)).await?;
-        self.wait_for(C0::SOH).await?;
-        let mut rom = [0; 3];
-        self.read(&mut rom).await?;
-        self.expect_read(C0::EOL).await?;
-        Ok(rom.into())
+    async fn write_str_lossy(&mut self, s: &str) -> Result<usize> {
+        self.write_str_lossy_with(s, '?').await
+    }
+
+    /// Write a string, replacing the characters that can not be displayed by `replacement`
+    ///
+    /// Returns the number of replaced characters. Fails with [`ErrorKind::InvalidInput`]
+    /// before writing anything if the replacement is not a G0 character, as
+    /// [`SIChar::lossy_with`].
+    async fn write_str_lossy_with(&mut self, s: &str, replacement: char) -> Result<usize> {
+        if G0::try_from(replacement).is_err() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Replacement {replacement:?} is not a G0 character"),
+            ));
+        }
+        let mut replaced = 0;
+        let text: String = s
+            .chars()
+            .map(|c| {
+                if SIChar::try_from(c).is_ok() {
+                    c
+                } else {
+                    replaced += 1;
+                    replacement
+                }
+            })
+            .collect();
+        self.write_str(&text).await?;
+        Ok(replaced)
     }
 
+    /// Show or hide the cursor
     #[inline(always)]
-    async fn get_pos(&mut self) -> Result<(u8, u8)> {
-        self.send(C1::EnqCursor).await?;
-        self.wait_for(C0::US).await?;
-        let mut position = [0; 2];
-        self.read(&mut position).await?;
-        Ok((position[1] - 0x40 - 1, position[0] - 0x40 - 1))
+    async fn set_cursor_visible(&mut self, visible: bool) -> Result<()> {
+        self.send(if visible { C0::Con } else { C0::Coff }).await
     }
 
+    /// Send a CSI sequence, to move the cursor or erase parts of the screen
     #[inline(always)]
-    async fn set_function_mode(&mut self, mode: FunctionMode, enable: bool) -> Result<()> {
-        self.send(ProtocolMessage::function_mode(mode, enable))
-            .await?;
-        let _status = self.read_pro2(Pro2Resp::RepStatus).await?;
-        Ok(())
+    async fn write_csi(&mut self, seq: CsiSequence) -> Result<()> {
+        self.send(seq).await
     }
 
+    /// Clear the screen and move the cursor to the first row
     #[inline(always)]
-    async fn set_routing(
-        &mut self,
-        enable: bool,
-        recepter: RoutingRx,
-        emitter: RoutingTx,
-    ) -> Result<()> {
-        self.send(ProtocolMessage::aiguillage(enable, emitter, recepter))
-            .await?;
-        let (_recepter, _status) = self.read_pro3(Pro3Resp::RoutingFrom).await?;
-        Ok(())
+    async fn clear_screen(&mut self) -> Result<()> {
+        self.send(C0::FF).await
     }
 
+    /// Move the cursor to the first position of the first row, keeping the screen content
     #[inline(always)]
-    async fn get_speed(&mut self) -> Result<Baudrate> {
-        self.send(ProtocolMessage::Pro1(Pro1::EnqSpeed)).await?;
-        let code = self.read_pro2(Pro2Resp::QuerySpeedAnswer).await?;
-        Baudrate::try_from(code).map_err(|_| ErrorKind::InvalidData.into())
+    async fn cursor_home(&mut self) -> Result<()> {
+        self.send(C0::RS).await
     }
-}
 
-/// Ability to communicate with a minitel through a serial port with baudrate control
-#[allow(async_fn_in_trait)]
-pub trait AsyncMinitelReadWriteBaudrate:
-    AsyncMinitelReadWrite + AsyncMinitelBaudrateControl
-{
-    async fn search_speed(&mut self) -> Result<Baudrate> {
-        for baudrate in [
-            Baudrate::B1200,
-            Baudrate::B9600,
-            Baudrate::B300,
-            Baudrate::B4800,
-        ] {
-            log::info!("Trying baudrate: {}", baudrate);
-            self.flush().await?;
-            self.set_baudrate(baudrate)?;
-            self.send(ProtocolMessage::Pro1(Pro1::EnqSpeed)).await?;
-            if let Ok(speed) = self.get_speed_blocking() {
-                log::info!("Found baudrate: {}", speed);
-                return Ok(speed);
+    /// Move the cursor to the beginning of its row
+    #[inline(always)]
+    async fn cursor_start_of_line(&mut self) -> Result<()> {
+        self.send(C0::CR).await
+    }
+
+    /// Clear the row of the cursor, without moving the cursor
+    #[inline(always)]
+    async fn clear_line(&mut self) -> Result<()> {
+        self.write_csi(CsiSequence::EraseLine).await
+    }
+
+    /// Clear a row, leaving the cursor at its beginning
+    async fn clear_line_at(&mut self, y: u8) -> Result<()> {
+        self.set_pos(0, y).await?;
+        self.clear_line().await
+    }
+
+    /// Clear from the cursor to the end of the screen, without moving the cursor
+    #[inline(always)]
+    async fn clear_to_end_of_screen(&mut self) -> Result<()> {
+        self.write_csi(CsiSequence::EraseToEndOfScreen).await
+    }
+
+    /// Ring the minitel bell
+    #[inline(always)]
+    async fn bell(&mut self) -> Result<()> {
+        self.send(C0::BEL).await
+    }
+
+    /// Ring the minitel bell, as [`Self::bell`]
+    #[inline(always)]
+    async fn beep(&mut self) -> Result<()> {
+        self.bell().await
+    }
+
+    /// Ring the minitel bell, extending it with a repetition of up to 63 units
+    ///
+    /// The STUM does not specify the duration of the beep in milliseconds: each unit
+    /// replays it once, so the actual duration depends on the terminal.
+    async fn bell_extended(&mut self, duration_units: u8) -> Result<()> {
+        if duration_units > 63 {
+            return Err(ErrorKind::InvalidInput.into());
+        }
+        let mut message = C0::BEL.message();
+        if duration_units > 0 {
+            message.extend(Repeat(duration_units).message());
+        }
+        self.write(&message).await
+    }
+
+    /// Move the cursor
+    ///
+    /// Coordinates are the ones of [`SetPosition`]:
+    /// - `x` is the column, from 0 to 39. It is sent as `0x40 + x + 1`, the first column
+    ///   being addressed as 0x41.
+    /// - `y` is the row, from 0 to 24. 0 is the status row, 1 to 24 are the rows of the page.
+    ///
+    /// Out of bounds positions fail with [`ErrorKind::InvalidInput`].
+    async fn set_pos(&mut self, x: u8, y: u8) -> Result<()> {
+        if x > 39 || y > 24 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Position ({x}, {y}) is out of the screen"),
+            ));
+        }
+        self.send(SetPosition(x, y)).await
+    }
+
+    /// Move the cursor, then write the text
+    ///
+    /// Out of bounds positions fail as with [`Self::set_pos`], before sending anything.
+    async fn write_at(&mut self, x: u8, y: u8, s: &str) -> Result<()> {
+        self.set_pos(x, y).await?;
+        self.write_str(s).await
+    }
+
+    /// Repeat the last displayed character `n` times, `n` being from 1 to 63
+    async fn write_repeat(&mut self, n: u8) -> Result<()> {
+        if !(1..=63).contains(&n) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Can not repeat a character {n} times"),
+            ));
+        }
+        self.send(Repeat(n)).await
+    }
+
+    /// Write a character, then repeat it `n` more times
+    ///
+    /// Only the single byte G0 characters can be repeated, other characters and counts
+    /// out of 1 to 63 fail with [`ErrorKind::InvalidInput`].
+    async fn repeat_char(&mut self, c: char, n: u8) -> Result<()> {
+        let g0 = G0::try_from(c).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Can not repeat {c:?}, not a G0 character"),
+            )
+        })?;
+        if !(1..=63).contains(&n) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Can not repeat a character {n} times"),
+            ));
+        }
+        self.write(&[g0.into(), C0::Rep.into(), 0x40 + n]).await
+    }
+
+    /// Write a byte `count` times, using the repetition control code
+    ///
+    /// The byte is sent once, then repeated by chunks of up to 63.
+    async fn write_byte_repeated(&mut self, byte: impl Into<u8>, count: u8) -> Result<()> {
+        if count == 0 {
+            return Ok(());
+        }
+        let mut message = vec![byte.into()];
+        extend_repeated(&mut message, count - 1);
+        self.write(&message).await
+    }
+
+    /// Fill a row with a character
+    ///
+    /// As for any zone attribute, the background applies after the next space.
+    async fn fill_row(&mut self, y: u8, ch: char, fg: C1, bg: C1) -> Result<()> {
+        self.fill_rect(0, y, 40, 1, ch, fg, bg).await
+    }
+
+    /// Fill a column of the screen with a character
+    ///
+    /// As for any zone attribute, the background applies after the next space.
+    async fn fill_column(&mut self, x: u8, ch: char, fg: C1, bg: C1) -> Result<()> {
+        self.fill_rect(x, 1, 1, 24, ch, fg, bg).await
+    }
+
+    /// Fill a rectangle with a character
+    ///
+    /// Each row is positioned, colored, then filled with the repetition control code.
+    /// As for any zone attribute, the background applies after the next space.
+    /// Fails with [`ErrorKind::InvalidInput`] if the rectangle is not on the screen.
+    #[allow(clippy::too_many_arguments)]
+    async fn fill_rect(
+        &mut self,
+        x: u8,
+        y: u8,
+        width: u8,
+        height: u8,
+        ch: char,
+        fg: C1,
+        bg: C1,
+    ) -> Result<()> {
+        let ch = SIChar::try_from(ch).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("{ch:?} can not be displayed"),
+            )
+        })?;
+        check_area(x, y, width as usize, height as usize)?;
+        if width == 0 {
+            return Ok(());
+        }
+        let mut message = Vec::new();
+        for row in y..y.saturating_add(height) {
+            message.extend(colored_text(x, row, "", fg, bg));
+            message.extend(ch.message());
+            extend_repeated(&mut message, width - 1);
+        }
+        self.write(&message).await
+    }
+
+    /// Draw a semi-graphic image, its top left corner being at (x, y)
+    ///
+    /// Each row is positioned, switched to G1 and colored, then written with the
+    /// repetition control code for consecutive identical characters.
+    /// Fails with [`ErrorKind::InvalidInput`] if the image is not on the screen.
+    async fn write_g1_bitmap(
+        &mut self,
+        x: u8,
+        y: u8,
+        data: &[Vec<G1>],
+        fg: C1,
+        bg: C1,
+    ) -> Result<()> {
+        let width = data.iter().map(Vec::len).max().unwrap_or_default();
+        check_area(x, y, width, data.len())?;
+        let mut message = Vec::new();
+        for (row, chars) in (y..).zip(data) {
+            message.extend(SetPosition(x, row).message());
+            message.push(C0::SO.into());
+            message.extend(fg.message());
+            message.extend(bg.message());
+            for run in chars.chunk_by(|a, b| a == b) {
+                message.push(run[0].into());
+                extend_repeated(&mut message, (run.len() - 1) as u8);
             }
         }
-        Err(ErrorKind::NotFound.into())
+        self.write(&message).await
     }
 
-    fn get_speed_blocking(&mut self) -> Result<Baudrate> {
-        // blocking read, can't make async timeout work on esp
-        for _ in 0..10 {
-            if let Ok(C0::ESC) = self.read_byte_blocking().map(C0::from) {
-                if let Ok(C1::Pro2) = self.read_byte_blocking().map(C1::from) {
-                    if let Ok(Ok(Pro2Resp::QuerySpeedAnswer)) =
-                        self.read_byte_blocking().map(Pro2Resp::try_from)
-                    {
-                        let code = self.read_byte_blocking()?;
-                        return Baudrate::try_from(code).map_err(|_| ErrorKind::InvalidData.into());
-                    }
+    /// Draw a colored semigraphic canvas with its top left corner at (x, y)
+    ///
+    /// The colors are only sent when they change along a row.
+    /// Fails with [`ErrorKind::InvalidInput`] if the canvas is not on the screen.
+    async fn write_colored_canvas(&mut self, x: u8, y: u8, canvas: &ColoredCanvas) -> Result<()> {
+        let cells = canvas.cells();
+        let width = cells.iter().map(Vec::len).max().unwrap_or_default();
+        check_area(x, y, width, cells.len())?;
+        let mut message = Vec::new();
+        for (row, cells) in (y..).zip(canvas.cells()) {
+            message.extend(SetPosition(x, row).message());
+            message.push(C0::SO.into());
+            // The position resets the colors
+            let mut colors = ColorPair::default();
+            for run in cells.chunk_by(|a, b| a == b) {
+                let (g1, fg, bg) = run[0];
+                let run_colors = ColorPair::new(fg, bg);
+                for attribute in ColorPair::diff(&colors, &run_colors) {
+                    message.extend(attribute.message());
                 }
+                colors = run_colors;
+                message.push(g1.into());
+                extend_repeated(&mut message, (run.len() - 1) as u8);
             }
         }
-        Err(ErrorKind::NotFound.into())
+        self.write(&message).await
     }
 
+    /// Write the next characters on two rows, the cursor row being the bottom one
     #[inline(always)]
-    async fn set_speed(&mut self, baudrate: Baudrate) -> Result<Baudrate> {
-        self.send(ProtocolMessage::Pro2(Pro2::Prog, baudrate.code()))
-            .await?;
-        self.flush().await?;
-        self.set_baudrate(baudrate)?;
+    async fn double_height(&mut self) -> Result<()> {
+        self.send(C1::DoubleHeight).await
+    }
 
-        let speed_code = self.read_pro2(Pro2Resp::QuerySpeedAnswer).await?;
-        let baudrate = Baudrate::try_from(speed_code).map_err(|_| ErrorKind::InvalidData)?;
-        Ok(baudrate)
+    /// Write the next characters on two columns
+    #[inline(always)]
+    async fn double_width(&mut self) -> Result<()> {
+        self.send(C1::DoubleWidth).await
     }
-}
 
-impl<T> AsyncMinitelReadWrite for T where T: AsyncMinitelRead + AsyncMinitelWrite {}
-impl<T> AsyncMinitelReadWriteBaudrate for T where
-    T: AsyncMinitelRead + AsyncMinitelWrite + AsyncMinitelBaudrateControl
-{
-}
+    /// Write the next characters on two rows and two columns
+    #[inline(always)]
+    async fn double_size(&mut self) -> Result<()> {
+        self.send(C1::DoubleSize).await
+    }
 
-#[cfg(test)]
-#[cfg(feature = "futures")]
-mod tests {
-    use ::futures::io::Cursor;
-    use stum::videotex::StringMessage;
+    /// Go back to the normal size for the next characters
+    #[inline(always)]
+    async fn normal_size(&mut self) -> Result<()> {
+        self.send(C1::NormalSize).await
+    }
 
-    use super::*;
-    #[tokio::test]
-    async fn read_stroke() {
-        let seq: Vec<_> = "He?! ".bytes().collect();
-        let mut minitel = Cursor::new(seq);
-        assert_eq!(
-            minitel.read_s0_stroke().await.unwrap(),
-            UserInput::Char('H')
-        );
-        assert_eq!(
-            minitel.read_s0_stroke().await.unwrap(),
-            UserInput::Char('e')
-        );
-        assert_eq!(
-            minitel.read_s0_stroke().await.unwrap(),
-            UserInput::Char('?')
-        );
-        assert_eq!(
-            minitel.read_s0_stroke().await.unwrap(),
-            UserInput::Char('!')
-        );
-        assert_eq!(
-            minitel.read_s0_stroke().await.unwrap(),
-            UserInput::Char(' ')
+    /// Mask the zone starting at the next space
+    ///
+    /// As a zone attribute, masking applies after the next space. The masked text is
+    /// displayed once the whole screen is unmasked with [`Self::set_screen_mask`],
+    /// revealing all the masked zones at once, for instance the answers of a quiz.
+    /// <https://jbellue.github.io/stum1b/#2-2-1-2-4-2>
+    #[inline(always)]
+    async fn mask_zone(&mut self) -> Result<()> {
+        self.send(C1::Mask).await
+    }
+
+    /// Stop masking from the zone starting at the next space
+    #[inline(always)]
+    async fn unmask_zone(&mut self) -> Result<()> {
+        self.send(C1::Unmask).await
+    }
+
+    /// Hide or reveal the masked zones of the whole screen
+    ///
+    /// Sends ESC 2/3 2/0 5/8 to mask the screen, or ESC 2/3 2/0 5/15 to unmask it.
+    async fn set_screen_mask(&mut self, masked: bool) -> Result<()> {
+        let attribute = if masked { C1::Mask } else { C1::Unmask };
+        self.write(&[C0::ESC.into(), 0x23, 0x20, attribute.into()])
+            .await
+    }
+
+    /// Invert the background of the zone starting at the next space, or restore it
+    #[inline(always)]
+    async fn set_invert_bg(&mut self, invert: bool) -> Result<()> {
+        self.send(if invert { C1::InvertBg } else { C1::NormalBg })
+            .await
+    }
+
+    /// Write a colored text at the beginning of a row
+    ///
+    /// As for any zone attribute, the background applies after the next space.
+    async fn write_left_aligned(&mut self, row: u8, text: &str, fg: C1, bg: C1) -> Result<()> {
+        self.write(&colored_text(0, row, text, fg, bg)).await
+    }
+
+    /// Write a colored text centered on a row
+    ///
+    /// As for any zone attribute, the background applies after the next space.
+    async fn write_centered(&mut self, row: u8, text: &str, fg: C1, bg: C1) -> Result<()> {
+        let column = 40usize.saturating_sub(display_width(text)) / 2;
+        self.write(&colored_text(column as u8, row, text, fg, bg))
+            .await
+    }
+
+    /// Write a colored text at the end of a row
+    ///
+    /// As for any zone attribute, the background applies after the next space.
+    async fn write_right_aligned(&mut self, row: u8, text: &str, fg: C1, bg: C1) -> Result<()> {
+        let column = 40usize.saturating_sub(display_width(text));
+        self.write(&colored_text(column as u8, row, text, fg, bg))
+            .await
+    }
+
+    /// Leave the minitel in a clean state: cleared screen and visible cursor
+    ///
+    /// To be called before dropping the port, as the cleanup can not happen on drop
+    /// with an async port.
+    async fn close(&mut self) -> Result<()> {
+        self.write(&[C0::FF.into(), C0::Con.into()]).await?;
+        self.flush().await
+    }
+
+    /// Writer tracking the column of the cursor, see [`WritingContext`]
+    fn writing_context(&mut self) -> WritingContext<'_, Self>
+    where
+        Self: Sized,
+    {
+        WritingContext::new(self)
+    }
+}
+
+/// Writer tracking the column of the cursor, to wrap or truncate the text
+///
+/// The cursor is assumed to start at the beginning of a row, use [`WritingContext::set_pos`]
+/// otherwise. Writing on the last column moves the cursor to the next row.
+pub struct WritingContext<'a, W> {
+    minitel: &'a mut W,
+    col: u8,
+}
+
+impl<'a, W: AsyncMinitelWrite> WritingContext<'a, W> {
+    pub fn new(minitel: &'a mut W) -> Self {
+        Self { minitel, col: 0 }
+    }
+
+    /// Column of the cursor, from 0 to 39
+    pub fn current_col(&self) -> u8 {
+        self.col
+    }
+
+    fn advance(&mut self, n: usize) {
+        self.col = ((self.col as usize + n) % 40) as u8;
+    }
+
+    /// Write a character, skipped if it can not be encoded
+    pub async fn write_char(&mut self, c: char) -> Result<()> {
+        let Ok(c) = SIChar::try_from(c) else {
+            return Ok(());
+        };
+        let mut buf: SmallVec<[u8; 3]> = SmallVec::new();
+        c.encode_to(&mut buf);
+        self.minitel.write(&buf).await?;
+        self.advance(1);
+        Ok(())
+    }
+
+    pub async fn write_g2(&mut self, g2: G2) -> Result<()> {
+        self.minitel.send(g2).await?;
+        self.advance(1);
+        Ok(())
+    }
+
+    /// Repeat the last character, see [`AsyncMinitelWrite::write_repeat`]
+    pub async fn write_repeat(&mut self, n: u8) -> Result<()> {
+        self.minitel.write_repeat(n).await?;
+        self.advance(n as usize);
+        Ok(())
+    }
+
+    pub async fn set_pos(&mut self, x: u8, y: u8) -> Result<()> {
+        self.minitel.set_pos(x, y).await?;
+        self.col = x;
+        Ok(())
+    }
+
+    /// Move the cursor to the beginning of the next row
+    pub async fn new_line(&mut self) -> Result<()> {
+        self.minitel.write(&[C0::CR.into(), C0::LF.into()]).await?;
+        self.col = 0;
+        Ok(())
+    }
+
+    pub async fn write_str(&mut self, text: &str) -> Result<()> {
+        self.minitel.write_str(text).await?;
+        self.advance(display_width(text));
+        Ok(())
+    }
+
+    /// Write a text, starting a new row instead of splitting a word
+    ///
+    /// Words longer than a row are split by the minitel.
+    pub async fn write_str_wrapped(&mut self, text: &str) -> Result<()> {
+        for (i, word) in text.split(' ').enumerate() {
+            let width = display_width(word);
+            if self.col > 0 && self.col as usize + (i > 0) as usize + width > 40 {
+                self.new_line().await?;
+            } else if i > 0 && self.col > 0 {
+                self.write_char(' ').await?;
+            }
+            self.write_str(word).await?;
+        }
+        Ok(())
+    }
+
+    /// Write at most `max_cols` characters of the text
+    pub async fn write_str_truncated(&mut self, text: &str, max_cols: u8) -> Result<()> {
+        let end = text
+            .char_indices()
+            .filter(|(_, c)| SIChar::try_from(*c).is_ok())
+            .nth(max_cols as usize)
+            .map_or(text.len(), |(i, _)| i);
+        self.write_str(&text[..end]).await
+    }
+}
+
+/// Output of the future, `None` if the timer completes first
+async fn race<T>(future: impl Future<Output = T>, timer: impl Future<Output = ()>) -> Option<T> {
+    let mut future = pin!(future);
+    let mut timer = pin!(timer);
+    poll_fn(|cx| match future.as_mut().poll(cx) {
+        Poll::Ready(output) => Poll::Ready(Some(output)),
+        Poll::Pending => timer.as_mut().poll(cx).map(|()| None),
+    })
+    .await
+}
+
+/// Write the digits at the end of the buffer, returning them
+fn encode_decimal(mut n: u32, buf: &mut [u8]) -> &[u8] {
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            return &buf[start..];
+        }
+    }
+}
+
+/// Fail with [`ErrorKind::InvalidInput`] if the area of `width`×`height` characters
+/// starting at (x, y) is not on the screen, counted as in [`SetPosition`]
+fn check_area(x: u8, y: u8, width: usize, height: usize) -> Result<()> {
+    if x > 39 || y > 24 || x as usize + width > 40 || y as usize + height > 25 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Area of {width}×{height} at ({x}, {y}) is out of the screen"),
+        ));
+    }
+    Ok(())
+}
+
+/// Repeat the last character `count` times, by chunks of up to 63
+fn extend_repeated(message: &mut Vec<u8>, mut count: u8) {
+    while count > 0 {
+        let n = count.min(63);
+        message.extend(Repeat(n).message());
+        count -= n;
+    }
+}
+
+/// Number of times a DLE frame is sent again after being rejected
+const FRAME_RETRIES: usize = 3;
+
+/// XOR of the data bytes, closing a DLE frame
+fn frame_checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |acc, byte| acc ^ byte)
+}
+
+/// DLE STX, the data with doubled DLE bytes, DLE ETX, then the checksum
+fn dle_frame(data: &[u8]) -> Vec<u8> {
+    let mut frame = vec![C0::DLE.into(), C0::STX.into()];
+    for &byte in data {
+        if C0::from(byte) == C0::DLE {
+            frame.push(byte);
+        }
+        frame.push(byte);
+    }
+    frame.extend([C0::DLE.into(), C0::ETX.into(), frame_checksum(data)]);
+    frame
+}
+
+/// Position, colors and text in a single message
+fn colored_text(x: u8, y: u8, text: &str, fg: C1, bg: C1) -> Vec<u8> {
+    let mut message = SetPosition(x, y).message();
+    message.extend(fg.message());
+    message.extend(bg.message());
+    StringMessage(text.to_string()).message_into(&mut message);
+    message
+}
+
+/// Raw videotex page, checked at compile time with [`stum::videotex::validate_page`]
+///
+/// ```
+/// const HOME: &[u8] = minitel::minitel_page!(b"\x0c\x1f\x41\x41\x1b\x41Bonjour");
+/// ```
+///
+/// A position outside of the screen fails to compile:
+///
+/// ```compile_fail
+/// const HOME: &[u8] = minitel::minitel_page!(b"\x1f\x41\x70Bonjour");
+/// ```
+#[macro_export]
+macro_rules! minitel_page {
+    ($page:expr) => {{
+        const PAGE: &[u8] = $page;
+        const _: () = match $crate::stum::videotex::validate_page(PAGE) {
+            Ok(()) => {}
+            Err(error) => panic!("{}", error.message()),
+        };
+        PAGE
+    }};
+}
+
+/// Number of bytes read by [`AsyncMinitelRead::wait_for`] while looking for the expected one
+///
+/// Slow or noisy links may need more, with [`AsyncMinitelRead::wait_for_n`].
+pub const DEFAULT_WAIT_RETRIES: usize = 10;
+
+/// Ability to change the baudrate of the serial port
+pub trait BaudrateControl {
+    /// Change the baudrate of the serial port
+    fn set_baudrate(&mut self, baudrate: Baudrate) -> Result<()>;
+}
+
+/// Ability to read a byte without async, used during the speed detection
+pub trait BlockingByteRead {
+    /// Read, non async
+    fn read_byte_blocking(&mut self) -> Result<u8>;
+}
+
+/// Ability to give up reading after a delay, for the serial ports supporting timeouts
+///
+/// Detects a disconnected minitel instead of waiting forever for its next key stroke.
+/// With an async runtime, the reads can also be wrapped in its own timeout.
+pub trait ReadTimeout {
+    /// Read a byte, `None` if nothing was received before the timeout
+    fn read_byte_timeout(&mut self, timeout: Duration) -> Result<Option<u8>>;
+}
+
+#[allow(async_fn_in_trait)]
+pub trait AsyncMinitelReadWrite: AsyncMinitelRead + AsyncMinitelWrite {
+    #[inline(always)]
+    async fn read_rom(&mut self) -> Result<Rom> {
+        self.send(ProtocolMessage::Pro1(Pro1::EnqRom)).await?;
+        self.wait_for(C0::SOH).await?;
+        let mut rom = [0; 3];
+        self.read(&mut rom).await?;
+        self.expect_read(C0::EOL).await?;
+        Ok(rom.into())
+    }
+
+    /// Wait for the minitel to answer the ROM enquiry, while it boots
+    ///
+    /// The enquiry is sent again every 100ms until the timeout. `sleep` is the timer of the
+    /// async runtime, such as `tokio::time::sleep`. An enquiry still unanswered when its
+    /// timer completes is abandoned, along with the bytes of its partial answer.
+    async fn wait_for_connection<F: Future<Output = ()>>(
+        &mut self,
+        timeout: Duration,
+        sleep: impl Fn(Duration) -> F,
+    ) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let delay = deadline
+                .saturating_duration_since(Instant::now())
+                .min(Duration::from_millis(100));
+            let mut timer = pin!(sleep(delay));
+            match race(self.read_rom(), timer.as_mut()).await {
+                Some(Ok(rom)) => {
+                    log::info!("Minitel connected: {}", rom.display_name());
+                    return Ok(());
+                }
+                // Failed before the end of the delay, wait for the next enquiry
+                Some(Err(_)) => timer.await,
+                None => {}
+            }
+            if Instant::now() >= deadline {
+                return Err(ErrorKind::TimedOut.into());
+            }
+        }
+    }
+
+    /// Query the cursor position
+    ///
+    /// The position is counted as in [`SetPosition`], the row 0 being the status row.
+    /// Fails with a [`MinitelProtocolError`] if the minitel answers with a row below
+    /// 0x40 or a column below 0x41.
+    #[inline(always)]
+    async fn get_pos(&mut self) -> Result<(u8, u8)> {
+        self.send(C1::EnqCursor).await?;
+        self.wait_for(C0::US).await?;
+        let mut position = [0; 2];
+        self.read(&mut position).await?;
+        let row = position[0]
+            .checked_sub(0x40)
+            .ok_or(MinitelProtocolError::UnexpectedByte {
+                step: "reading the cursor row".to_string(),
+                expected: 0x40,
+                got: position[0],
+            })?;
+        let col = position[1]
+            .checked_sub(0x41)
+            .ok_or(MinitelProtocolError::UnexpectedByte {
+                step: "reading the cursor column".to_string(),
+                expected: 0x41,
+                got: position[1],
+            })?;
+        Ok((col, row))
+    }
+
+    /// Paint a region of a row with a background color, then move the cursor back
+    ///
+    /// The region is overwritten with spaces, its previous content is lost. Use
+    /// [`AsyncMinitelReadWrite::unhighlight_region`] to write it back.
+    /// Fails with [`ErrorKind::InvalidInput`] if the region does not fit on the row.
+    async fn highlight_region(&mut self, x: u8, y: u8, length: u8, fg: C1, bg: C1) -> Result<()> {
+        check_area(x, y, length as usize, 1)?;
+        let (cursor_x, cursor_y) = self.get_pos().await?;
+        let mut message = SetPosition(x, y).message();
+        message.extend(fg.message());
+        message.extend(bg.message());
+        if length > 0 {
+            message.push(b' ');
+            extend_repeated(&mut message, length - 1);
+        }
+        message.extend(SetPosition(cursor_x, cursor_y).message());
+        self.write(&message).await
+    }
+
+    /// Write back the content of a region painted by [`AsyncMinitelReadWrite::highlight_region`]
+    ///
+    /// The minitel screen can not be read, the caller keeps the previous content of the region
+    /// and its colors. The cursor is then moved back. Fails with [`ErrorKind::InvalidInput`]
+    /// if the content does not fit on the row.
+    async fn unhighlight_region(
+        &mut self,
+        x: u8,
+        y: u8,
+        content: &str,
+        fg: C1,
+        bg: C1,
+    ) -> Result<()> {
+        check_area(x, y, display_width(content), 1)?;
+        let (cursor_x, cursor_y) = self.get_pos().await?;
+        let mut message = colored_text(x, y, content, fg, bg);
+        message.extend(SetPosition(cursor_x, cursor_y).message());
+        self.write(&message).await
+    }
+
+    #[inline(always)]
+    async fn set_function_mode(&mut self, mode: FunctionMode, enable: bool) -> Result<()> {
+        self.send(ProtocolMessage::function_mode(mode, enable))
+            .await?;
+        let _status = self.read_pro2(Pro2Resp::RepStatus).await?;
+        Ok(())
+    }
+
+    #[inline(always)]
+    async fn get_function_status(&mut self) -> Result<FunctionModeStatus> {
+        self.send(ProtocolMessage::Pro1(Pro1::EnqFunctionStatus))
+            .await?;
+        let status = self.read_pro2(Pro2Resp::RepStatus).await?;
+        Ok(status.into())
+    }
+
+    /// Whether the keyboard sends lowercase letters
+    ///
+    /// The status is enquired, the mode is left untouched.
+    async fn query_minuscule_mode(&mut self) -> Result<bool> {
+        Ok(self.get_function_status().await?.minuscule)
+    }
+
+    /// Read the raw terminal status byte
+    #[inline(always)]
+    async fn get_terminal_status(&mut self) -> Result<u8> {
+        self.send(ProtocolMessage::Pro1(Pro1::EnqTerminalStatus))
+            .await?;
+        self.read_pro2(Pro2Resp::RepTerminalStatus).await
+    }
+
+    /// Read the raw protocol status byte
+    #[inline(always)]
+    async fn get_protocol_status(&mut self) -> Result<u8> {
+        self.send(ProtocolMessage::Pro1(Pro1::EnqProtocolStatus))
+            .await?;
+        self.read_pro2(Pro2Resp::RepProtocolStatus).await
+    }
+
+    /// Scroll the screen up by n lines, temporarily enabling the rouleau mode if needed
+    ///
+    /// The screen only scrolls when the cursor is on the last row.
+    async fn scroll_up_n(&mut self, n: u8) -> Result<()> {
+        let rouleau = self.get_function_status().await?.rouleau;
+        if !rouleau {
+            self.set_function_mode(FunctionMode::Rouleau, true).await?;
+        }
+        self.write(&vec![C0::LF.into(); n as usize]).await?;
+        if !rouleau {
+            self.set_function_mode(FunctionMode::Rouleau, false).await?;
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    async fn set_routing(
+        &mut self,
+        enable: bool,
+        recepter: RoutingRx,
+        emitter: RoutingTx,
+    ) -> Result<()> {
+        self.send(ProtocolMessage::aiguillage(enable, emitter, recepter))
+            .await?;
+        let (_recepter, _status) = self.read_pro3(Pro3Resp::RoutingFrom).await?;
+        Ok(())
+    }
+
+    /// Query which emitters are routed to a module, without changing the routing
+    ///
+    /// The STUM has no dedicated PRO3 query: the status is requested with `Pro2::RoutingTo`
+    /// and answered the same way as a routing change.
+    #[inline(always)]
+    async fn query_routing_status(&mut self, module: RoutingRx) -> Result<RoutingStatus> {
+        self.send(ProtocolMessage::Pro2(Pro2::RoutingTo, module.into()))
+            .await?;
+        let (_module, status) = self.read_pro3(Pro3Resp::RoutingFrom).await?;
+        Ok(status.into())
+    }
+
+    /// Send a frame to a peer application implementing the same framing
+    ///
+    /// The data is framed between DLE STX and DLE ETX, DLE bytes being doubled, and followed
+    /// by the XOR checksum of the data. The peer acknowledges with SYN, the frame is sent
+    /// again on NACK, up to 3 times.
+    ///
+    /// This is not the error correcting procedure of the minitel (PCE, enabled with
+    /// [`FunctionMode::Procedure`]): the minitel itself never answers these frames, and
+    /// displays them as is.
+    async fn write_dle_frame(&mut self, data: &[u8]) -> Result<()> {
+        let frame = dle_frame(data);
+        for _ in 0..=FRAME_RETRIES {
+            self.write(&frame).await?;
+            self.flush().await?;
+            match C0::from(self.read_byte().await?) {
+                C0::SYN => return Ok(()),
+                C0::NACK => log::warn!("Frame rejected, sending it again"),
+                other => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Unexpected frame acknowledgement {other}"),
+                    ))
+                }
+            }
+        }
+        Err(Error::new(
+            ErrorKind::TimedOut,
+            "Frame rejected too many times",
+        ))
+    }
+
+    /// Receive a frame from a peer application implementing the same framing
+    ///
+    /// Frames with an invalid checksum are answered with NACK, up to 3 times, and valid
+    /// ones with SYN. See [`AsyncMinitelReadWrite::write_dle_frame`] for the framing, which
+    /// is not the error correcting procedure of the minitel.
+    async fn read_dle_frame(&mut self) -> Result<Vec<u8>> {
+        for _ in 0..=FRAME_RETRIES {
+            self.wait_for(C0::DLE).await?;
+            self.expect_read(C0::STX).await?;
+            let mut data = Vec::new();
+            loop {
+                let byte = self.read_byte().await?;
+                if C0::from(byte) != C0::DLE {
+                    data.push(byte);
+                    continue;
+                }
+                match C0::from(self.read_byte().await?) {
+                    C0::DLE => data.push(byte),
+                    C0::ETX => break,
+                    other => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("Unexpected {other} after DLE in frame"),
+                        ))
+                    }
+                }
+            }
+            let checksum = self.read_byte().await?;
+            if checksum == frame_checksum(&data) {
+                self.send(C0::SYN).await?;
+                return Ok(data);
+            }
+            log::warn!("Invalid frame checksum, asking it again");
+            self.send(C0::NACK).await?;
+        }
+        Err(Error::new(
+            ErrorKind::InvalidData,
+            "Frame received invalid too many times",
+        ))
+    }
+
+    #[inline(always)]
+    async fn get_speed(&mut self) -> Result<Baudrate> {
+        self.send(ProtocolMessage::Pro1(Pro1::EnqSpeed)).await?;
+        let code = self.read_pro2(Pro2Resp::QuerySpeedAnswer).await?;
+        Baudrate::try_from(code).map_err(|_| ErrorKind::InvalidData.into())
+    }
+}
+
+/// Ability to communicate with a minitel through a serial port with baudrate control
+#[allow(async_fn_in_trait)]
+pub trait AsyncMinitelReadWriteBaudrate:
+    AsyncMinitelReadWrite + BaudrateControl + BlockingByteRead
+{
+    async fn search_speed(&mut self) -> Result<Baudrate> {
+        for baudrate in [
+            Baudrate::B1200,
+            Baudrate::B9600,
+            Baudrate::B300,
+            Baudrate::B4800,
+        ] {
+            log::info!("Trying baudrate: {}", baudrate);
+            self.flush().await?;
+            self.set_baudrate(baudrate)?;
+            self.send(ProtocolMessage::Pro1(Pro1::EnqSpeed)).await?;
+            if let Ok(speed) = self.get_speed_blocking() {
+                log::info!("Found baudrate: {}", speed);
+                return Ok(speed);
+            }
+        }
+        Err(ErrorKind::NotFound.into())
+    }
+
+    /// Search the speed, waiting longer between each attempt while the minitel boots
+    ///
+    /// The first attempt is immediate, the delay then starts at `initial_delay_ms` and
+    /// doubles after each failed attempt. `sleep` waits for the delay without blocking,
+    /// such as `tokio::time::sleep`.
+    async fn search_speed_with_backoff<F: Future<Output = ()>>(
+        &mut self,
+        max_retries: u8,
+        initial_delay_ms: u64,
+        sleep: impl Fn(Duration) -> F,
+    ) -> Result<Baudrate> {
+        let mut delay = Duration::from_millis(initial_delay_ms);
+        for _ in 0..max_retries {
+            match self.search_speed().await {
+                Err(e) if e.kind() == ErrorKind::NotFound => {
+                    log::info!("Minitel not found, retrying in {:?}", delay);
+                    sleep(delay).await;
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+        self.search_speed().await
+    }
+
+    fn get_speed_blocking(&mut self) -> Result<Baudrate> {
+        // blocking read, can't make async timeout work on esp
+        for _ in 0..10 {
+            if let Ok(C0::ESC) = self.read_byte_blocking().map(C0::from) {
+                if let Ok(C1::Pro2) = self.read_byte_blocking().map(C1::from) {
+                    if let Ok(Ok(Pro2Resp::QuerySpeedAnswer)) =
+                        self.read_byte_blocking().map(Pro2Resp::try_from)
+                    {
+                        let code = self.read_byte_blocking()?;
+                        return Baudrate::try_from(code).map_err(|_| ErrorKind::InvalidData.into());
+                    }
+                }
+            }
+        }
+        Err(ErrorKind::NotFound.into())
+    }
+
+    #[inline(always)]
+    async fn set_speed(&mut self, baudrate: Baudrate) -> Result<Baudrate> {
+        self.send(ProtocolMessage::Pro2(Pro2::Prog, baudrate.code()))
+            .await?;
+        self.flush().await?;
+        self.set_baudrate(baudrate)?;
+
+        let speed_code = self.read_pro2(Pro2Resp::QuerySpeedAnswer).await?;
+        let baudrate = Baudrate::try_from(speed_code).map_err(|_| ErrorKind::InvalidData)?;
+        Ok(baudrate)
+    }
+}
+
+impl<T> AsyncMinitelReadWrite for T where T: AsyncMinitelRead + AsyncMinitelWrite {}
+impl<T> AsyncMinitelReadWriteBaudrate for T where
+    T: AsyncMinitelRead + AsyncMinitelWrite + BaudrateControl + BlockingByteRead
+{
+}
+
+#[cfg(test)]
+#[cfg(feature = "futures")]
+mod tests {
+    use std::{cell::RefCell, collections::VecDeque};
+
+    use ::futures::io::Cursor;
+
+    use super::*;
+    use stum::protocol::RoutingMatrix;
+
+    /// Port with separate canned input and recorded output
+    #[derive(Default)]
+    pub(crate) struct MockPort {
+        pub(crate) input: VecDeque<u8>,
+        pub(crate) output: Vec<u8>,
+    }
+
+    impl MockPort {
+        pub(crate) fn new(input: &[u8]) -> Self {
+            Self {
+                input: input.iter().copied().collect(),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl AsyncMinitelRead for MockPort {
+        async fn read(&mut self, data: &mut [u8]) -> Result<()> {
+            for byte in data.iter_mut() {
+                *byte = self
+                    .input
+                    .pop_front()
+                    .ok_or(Error::from(ErrorKind::UnexpectedEof))?;
+            }
+            Ok(())
+        }
+    }
+
+    impl AsyncMinitelWrite for MockPort {
+        async fn write(&mut self, data: &[u8]) -> Result<()> {
+            self.output.extend_from_slice(data);
+            Ok(())
+        }
+
+        async fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn scroll_up_n() {
+        // Rouleau disabled: enabled, then disabled again
+        let mut minitel = MockPort::new(&[
+            0x1B, 0x3A, 0x73, 0x40, // status: no rouleau
+            0x1B, 0x3A, 0x73, 0x42, // status: rouleau
+            0x1B, 0x3A, 0x73, 0x40, // status: no rouleau
+        ]);
+        minitel.scroll_up_n(2).await.unwrap();
+        assert_eq!(
+            minitel.output,
+            vec![
+                0x1B, 0x39, 0x72, // PRO1 enquiry status
+                0x1B, 0x3A, 0x69, 0x43, // PRO2 start rouleau
+                0x0A, 0x0A, // LF LF
+                0x1B, 0x3A, 0x6A, 0x43, // PRO2 stop rouleau
+            ]
+        );
+
+        // Rouleau already enabled: left untouched
+        let mut minitel = MockPort::new(&[0x1B, 0x3A, 0x73, 0x42]);
+        minitel.scroll_up_n(1).await.unwrap();
+        assert_eq!(minitel.output, vec![0x1B, 0x39, 0x72, 0x0A]);
+    }
+    #[tokio::test]
+    async fn write_dle_frame() {
+        let frame = [
+            0x10,
+            0x02,
+            0x41,
+            0x10,
+            0x10,
+            0x42,
+            0x10,
+            0x03,
+            0x41 ^ 0x10 ^ 0x42,
+        ];
+
+        // Rejected once, then acknowledged
+        let mut minitel = MockPort::new(&[0x15, 0x16]);
+        minitel.write_dle_frame(&[0x41, 0x10, 0x42]).await.unwrap();
+        assert_eq!(minitel.output, [frame, frame].concat());
+
+        let mut minitel = MockPort::new(&[0x15; 4]);
+        let err = minitel.write_dle_frame(&[0x41]).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn read_dle_frame() {
+        // Invalid checksum, then the same frame sent again
+        let mut minitel = MockPort::new(&[
+            0x10, 0x02, 0x41, 0x10, 0x10, 0x10, 0x03,
+            0x00, // DLE STX A DLE DLE ETX bad checksum
+            0x10, 0x02, 0x41, 0x10, 0x10, 0x10, 0x03, 0x51, // DLE STX A DLE DLE ETX checksum
+        ]);
+        assert_eq!(minitel.read_dle_frame().await.unwrap(), vec![0x41, 0x10]);
+        assert_eq!(minitel.output, vec![0x15, 0x16]);
+
+        let mut minitel = MockPort::new(&[0x10, 0x02, 0x10, 0x41]);
+        let err = minitel.read_dle_frame().await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    /// Serial port answering the speed enquiry once the minitel is booted
+    #[derive(Default)]
+    struct BootingPort {
+        attempts_before_ready: usize,
+        silent_reads: usize,
+        answer: VecDeque<u8>,
+    }
+
+    impl AsyncMinitelRead for BootingPort {
+        async fn read(&mut self, data: &mut [u8]) -> Result<()> {
+            if self.silent_reads > 0 {
+                self.silent_reads -= 1;
+                return std::future::pending().await;
+            }
+            for byte in data.iter_mut() {
+                *byte = self.answer.pop_front().ok_or(ErrorKind::UnexpectedEof)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl AsyncMinitelWrite for BootingPort {
+        async fn write(&mut self, _data: &[u8]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl BaudrateControl for BootingPort {
+        fn set_baudrate(&mut self, _baudrate: Baudrate) -> Result<()> {
+            if self.attempts_before_ready == 0 {
+                // REP_SPEED 1200 bauds
+                self.answer = VecDeque::from([0x1B, 0x3A, 0x75, 0b01_100_100]);
+            } else {
+                self.attempts_before_ready -= 1;
+            }
+            Ok(())
+        }
+    }
+
+    impl BlockingByteRead for BootingPort {
+        fn read_byte_blocking(&mut self) -> Result<u8> {
+            self.answer.pop_front().ok_or(ErrorKind::TimedOut.into())
+        }
+    }
+
+    #[tokio::test]
+    async fn search_speed_with_backoff() {
+        // Not answering during the two first searches
+        let mut minitel = BootingPort {
+            attempts_before_ready: 8,
+            ..Default::default()
+        };
+        let delays = RefCell::new(Vec::new());
+        let sleep = |delay| {
+            delays.borrow_mut().push(delay);
+            async {}
+        };
+        let speed = minitel
+            .search_speed_with_backoff(2, 50, sleep)
+            .await
+            .unwrap();
+        assert!(matches!(speed, Baudrate::B1200));
+        assert_eq!(
+            *delays.borrow(),
+            [Duration::from_millis(50), Duration::from_millis(100)]
+        );
+
+        let mut minitel = BootingPort {
+            attempts_before_ready: 12,
+            ..Default::default()
+        };
+        let err = minitel
+            .search_speed_with_backoff(2, 50, |_| async {})
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn wait_for_connection() {
+        // Not answering the two first enquiries
+        let mut minitel = BootingPort {
+            silent_reads: 2,
+            answer: VecDeque::from([0x01, b'C', b'u', b'4', 0x04]),
+            ..Default::default()
+        };
+        minitel
+            .wait_for_connection(Duration::from_secs(1), tokio::time::sleep)
+            .await
+            .unwrap();
+        assert_eq!(minitel.silent_reads, 0);
+
+        // Never answering
+        let mut minitel = BootingPort {
+            silent_reads: usize::MAX,
+            ..Default::default()
+        };
+        let err = minitel
+            .wait_for_connection(Duration::from_millis(150), tokio::time::sleep)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+        // Failing to read
+        let mut minitel = BootingPort::default();
+        let err = minitel
+            .wait_for_connection(Duration::from_millis(150), tokio::time::sleep)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn read_stroke() {
+        let seq: Vec<_> = "He?! ".bytes().collect();
+        let mut minitel = Cursor::new(seq);
+        assert_eq!(
+            minitel.read_s0_stroke().await.unwrap(),
+            UserInput::Char('H')
+        );
+        assert_eq!(
+            minitel.read_s0_stroke().await.unwrap(),
+            UserInput::Char('e')
+        );
+        assert_eq!(
+            minitel.read_s0_stroke().await.unwrap(),
+            UserInput::Char('?')
+        );
+        assert_eq!(
+            minitel.read_s0_stroke().await.unwrap(),
+            UserInput::Char('!')
+        );
+        assert_eq!(
+            minitel.read_s0_stroke().await.unwrap(),
+            UserInput::Char(' ')
         );
 
         let seq: Vec<_> = vec![0x20, 0x13, 0x41, 0x13, 0x49, 0x20, 0x1B, 0x54];
@@ -334,6 +1602,607 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn routing_matrix_set_route() {
+        let mut matrix = RoutingMatrix::default_interactive();
+        let mut minitel = MockPort::new(&[0x1B, 0x3B, 0x63, 0x5A, 0b0010]);
+        // Already routed: nothing sent
+        matrix
+            .set_route(&mut minitel, RoutingTx::Keyboard, RoutingRx::Screen, true)
+            .await
+            .unwrap();
+        matrix
+            .set_route(&mut minitel, RoutingTx::Keyboard, RoutingRx::Modem, true)
+            .await
+            .unwrap();
+        assert_eq!(minitel.output, vec![0x1B, 0x3B, 0x61, 0x5A, 0x51]);
+        assert!(matrix.is_routed(RoutingTx::Keyboard, RoutingRx::Modem));
+    }
+
+    #[tokio::test]
+    async fn query_routing_status() {
+        let mut minitel = MockPort::new(&[0x1B, 0x3B, 0x63, 0x58, 0b0110]);
+        let status = minitel
+            .query_routing_status(RoutingRx::Screen)
+            .await
+            .unwrap();
+        assert_eq!(minitel.output, vec![0x1B, 0x3A, 0x62, 0x58]);
+        assert!(!status.prise);
+        assert!(status.modem);
+        assert!(status.keyboard);
+        assert!(!status.screen);
+    }
+
+    #[tokio::test]
+    async fn get_terminal_status() {
+        let mut minitel = MockPort::new(&[0x1B, 0x3A, 0x71, 0x42]);
+        assert_eq!(minitel.get_terminal_status().await.unwrap(), 0x42);
+        assert_eq!(minitel.output, vec![0x1B, 0x39, 0x70]);
+    }
+
+    #[tokio::test]
+    async fn query_minuscule_mode() {
+        let mut minitel = MockPort::new(&[0x1B, 0x3A, 0x73, 0x48]);
+        assert!(minitel.query_minuscule_mode().await.unwrap());
+        assert_eq!(minitel.output, vec![0x1B, 0x39, 0x72]);
+
+        let mut minitel = MockPort::new(&[0x1B, 0x3A, 0x73, 0x42]);
+        assert!(!minitel.query_minuscule_mode().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn read_s0_strokes_until() {
+        let mut minitel = MockPort::new(&[b'a', 0x13, 0x48, b'b']);
+        let strokes = minitel
+            .read_s0_strokes_until(|s| matches!(s, UserInput::FunctionKey(_)))
+            .await
+            .unwrap();
+        assert_eq!(
+            strokes,
+            vec![
+                UserInput::Char('a'),
+                UserInput::FunctionKey(FunctionKey::Suite)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn read_text_input() {
+        let mut minitel = MockPort::new(b"abc\x13\x47d\x13\x41");
+        assert_eq!(minitel.read_text_input(10).await.unwrap(), "abd");
+
+        let mut minitel = MockPort::new(b"abc\x13\x41");
+        assert_eq!(minitel.read_text_input(2).await.unwrap(), "ab");
+    }
+
+    #[tokio::test]
+    async fn read_line() {
+        let mut minitel = MockPort::new(b"abc\x08d\rxyz\x13\x41");
+        assert_eq!(minitel.read_line(40).await.unwrap(), "abd");
+        assert_eq!(minitel.read_line(2).await.unwrap(), "xy");
+
+        let mut minitel = MockPort::new(b"ab\x13\x49");
+        let err = minitel.read_line(40).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionAborted);
+    }
+
+    #[tokio::test]
+    async fn write_aligned() {
+        let mut minitel = MockPort::new(&[]);
+        minitel
+            .write_centered(1, "Télé", C1::CharRed, C1::BgBlue)
+            .await
+            .unwrap();
+        assert_eq!(
+            minitel.output,
+            vec![
+                0x1F, 0x41, 0x53, // row 1, column 18
+                0x1B, 0x41, 0x1B, 0x54, // red on blue
+                b'T', 0x19, 0x42, b'e', b'l', 0x19, 0x42, b'e',
+            ]
+        );
+
+        let mut minitel = MockPort::new(&[]);
+        minitel
+            .write_right_aligned(2, "Fin", C1::CharWhite, C1::BgBlack)
+            .await
+            .unwrap();
+        assert_eq!(minitel.output[..3], [0x1F, 0x42, 0x40 + 37 + 1]);
+    }
+
+    #[tokio::test]
+    async fn protocol_error_context() {
+        // REP_STATUS instead of REP_SPEED
+        let mut minitel = MockPort::new(&[0x1B, 0x3A, 0x73, 0x40]);
+        let err = minitel
+            .read_pro2(Pro2Resp::QuerySpeedAnswer)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let inner = err
+            .get_ref()
+            .unwrap()
+            .downcast_ref::<MinitelProtocolError>();
+        assert_eq!(
+            inner,
+            Some(&MinitelProtocolError::UnexpectedByte {
+                step: "waiting for QuerySpeedAnswer".to_string(),
+                expected: 0x75,
+                got: 0x73,
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            "Unexpected byte 0x73 instead of 0x75 while waiting for QuerySpeedAnswer"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_pos_invalid() {
+        let mut minitel = MockPort::new(&[0x1F, 0x3F, 0x41]);
+        let err = minitel.get_pos().await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            err.get_ref()
+                .and_then(|e| e.downcast_ref::<MinitelProtocolError>()),
+            Some(&MinitelProtocolError::UnexpectedByte {
+                step: "reading the cursor row".to_string(),
+                expected: 0x40,
+                got: 0x3F,
+            })
+        );
+
+        let mut minitel = MockPort::new(&[0x1F, 0x41, 0x40]);
+        let err = minitel.get_pos().await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unexpected byte 0x40 instead of 0x41 while reading the cursor column"
+        );
+    }
+
+    #[tokio::test]
+    async fn set_pos() {
+        let mut minitel = MockPort::new(&[]);
+        minitel.set_pos(0, 0).await.unwrap();
+        minitel.set_pos(39, 24).await.unwrap();
+        assert_eq!(minitel.output, vec![0x1F, 0x40, 0x41, 0x1F, 0x58, 0x68]);
+        for (x, y) in [(40, 1), (0, 25), (255, 255)] {
+            let err = minitel.set_pos(x, y).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    #[tokio::test]
+    async fn zone_attributes() {
+        let mut minitel = MockPort::new(&[]);
+        minitel.mask_zone().await.unwrap();
+        minitel.unmask_zone().await.unwrap();
+        minitel.set_screen_mask(true).await.unwrap();
+        minitel.set_screen_mask(false).await.unwrap();
+        minitel.set_invert_bg(true).await.unwrap();
+        minitel.set_invert_bg(false).await.unwrap();
+        minitel.set_cursor_visible(true).await.unwrap();
+        minitel.set_cursor_visible(false).await.unwrap();
+        assert_eq!(
+            minitel.output,
+            vec![
+                0x1B, 0x58, // mask zone
+                0x1B, 0x5F, // unmask zone
+                0x1B, 0x23, 0x20, 0x58, // mask screen
+                0x1B, 0x23, 0x20, 0x5F, // unmask screen
+                0x1B, 0x5D, 0x1B, 0x5C, // invert, normal background
+                0x11, 0x14, // cursor on, off
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn highlight_region() {
+        let mut minitel = MockPort::new(&[0x1F, 0x43, 0x45]);
+        minitel
+            .highlight_region(2, 5, 10, C1::CharBlack, C1::BgYellow)
+            .await
+            .unwrap();
+        assert_eq!(
+            minitel.output,
+            vec![
+                0x1B, 0x61, // cursor enquiry
+                0x1F, 0x45, 0x43, // region start
+                0x1B, 0x40, 0x1B, 0x53, // black on yellow
+                b' ', 0x12, 0x49, // 10 spaces
+                0x1F, 0x43, 0x45, // cursor restored
+            ]
+        );
+
+        // Longer than a repetition
+        let mut minitel = MockPort::new(&[0x1F, 0x43, 0x45]);
+        minitel
+            .highlight_region(0, 5, 40, C1::CharBlack, C1::BgYellow)
+            .await
+            .unwrap();
+        assert_eq!(minitel.output[9..12], [b' ', 0x12, 0x67]);
+
+        for (x, length) in [(30, 11), (40, 0), (0, 255)] {
+            let mut minitel = MockPort::new(&[0x1F, 0x43, 0x45]);
+            let err = minitel
+                .highlight_region(x, 5, length, C1::CharBlack, C1::BgYellow)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+            assert!(minitel.output.is_empty());
+        }
+
+        let mut minitel = MockPort::new(&[0x1F, 0x43, 0x45]);
+        minitel
+            .unhighlight_region(2, 5, "Minitel", C1::CharGreen, C1::BgBlue)
+            .await
+            .unwrap();
+        assert_eq!(
+            minitel.output,
+            [
+                &[0x1B, 0x61][..],         // cursor enquiry
+                &[0x1F, 0x45, 0x43],       // region start
+                &[0x1B, 0x42, 0x1B, 0x54], // green on blue
+                b"Minitel",
+                &[0x1F, 0x43, 0x45], // cursor restored
+            ]
+            .concat()
+        );
+    }
+
+    #[tokio::test]
+    async fn bell() {
+        let mut minitel = Cursor::new(Vec::new());
+        minitel.bell().await.unwrap();
+        minitel.bell_extended(3).await.unwrap();
+        minitel.bell_extended(0).await.unwrap();
+        minitel.beep().await.unwrap();
+        assert!(minitel.bell_extended(64).await.is_err());
+        assert_eq!(
+            minitel.into_inner(),
+            vec![0x07, 0x07, 0x12, 0x43, 0x07, 0x07]
+        );
+    }
+
+    #[tokio::test]
+    async fn write_repeat() {
+        let mut minitel = MockPort::new(&[]);
+        minitel.write_repeat(3).await.unwrap();
+        minitel.write_repeat(63).await.unwrap();
+        assert_eq!(minitel.output, vec![0x12, 0x43, 0x12, 0x7F]);
+        assert!(minitel.write_repeat(0).await.is_err());
+        assert!(minitel.write_repeat(64).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn character_size() {
+        let mut minitel = MockPort::new(&[]);
+        minitel.double_height().await.unwrap();
+        minitel.double_width().await.unwrap();
+        minitel.double_size().await.unwrap();
+        minitel.normal_size().await.unwrap();
+        assert_eq!(
+            minitel.output,
+            vec![0x1B, 0x4D, 0x1B, 0x4E, 0x1B, 0x4F, 0x1B, 0x4C]
+        );
+    }
+
+    #[tokio::test]
+    async fn repeat_char() {
+        let mut minitel = MockPort::new(&[]);
+        minitel.repeat_char('-', 39).await.unwrap();
+        assert_eq!(minitel.output, vec![b'-', 0x12, 0x40 + 39]);
+        for (c, n) in [('-', 0), ('-', 64), ('é', 3), ('£', 3)] {
+            let err = minitel.repeat_char(c, n).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    #[tokio::test]
+    async fn write_byte_repeated() {
+        let mut minitel = MockPort::new(&[]);
+        minitel.write_byte_repeated(b'-', 1).await.unwrap();
+        assert_eq!(minitel.output, vec![b'-']);
+
+        let mut minitel = MockPort::new(&[]);
+        minitel.write_byte_repeated(G1(0x7F), 40).await.unwrap();
+        assert_eq!(minitel.output, vec![0x7F, 0x12, 0x40 + 39]);
+
+        let mut minitel = MockPort::new(&[]);
+        minitel.write_byte_repeated(b' ', 100).await.unwrap();
+        minitel.write_byte_repeated(b' ', 0).await.unwrap();
+        assert_eq!(minitel.output, vec![b' ', 0x12, 0x7F, 0x12, 0x40 + 36]);
+    }
+
+    #[tokio::test]
+    async fn fill() {
+        let mut minitel = MockPort::new(&[]);
+        minitel
+            .fill_row(3, '-', C1::CharBlue, C1::BgBlack)
+            .await
+            .unwrap();
+        assert_eq!(
+            minitel.output,
+            vec![
+                0x1F,
+                0x43,
+                0x41, // row 3, column 0
+                0x1B,
+                0x44,
+                0x1B,
+                0x50, // blue on black
+                b'-',
+                0x12,
+                0x40 + 39, // 40 dashes
+            ]
+        );
+
+        let mut minitel = MockPort::new(&[]);
+        minitel
+            .fill_rect(2, 5, 3, 2, '#', C1::CharWhite, C1::BgBlack)
+            .await
+            .unwrap();
+        assert_eq!(
+            minitel.output,
+            vec![
+                0x1F, 0x45, 0x43, 0x1B, 0x47, 0x1B, 0x50, b'#', 0x12, 0x42, // row 5
+                0x1F, 0x46, 0x43, 0x1B, 0x47, 0x1B, 0x50, b'#', 0x12, 0x42, // row 6
+            ]
+        );
+
+        let mut minitel = MockPort::new(&[]);
+        minitel
+            .fill_column(39, '|', C1::CharWhite, C1::BgBlack)
+            .await
+            .unwrap();
+        assert_eq!(minitel.output.len(), 24 * 8);
+
+        let err = minitel
+            .fill_row(1, '☃', C1::CharWhite, C1::BgBlack)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        // Off the screen, nothing is written
+        for (x, y, width, height) in [(30, 20, 20, 10), (0, 24, 40, 2), (40, 1, 0, 1)] {
+            let mut minitel = MockPort::new(&[]);
+            let err = minitel
+                .fill_rect(x, y, width, height, '#', C1::CharWhite, C1::BgBlack)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+            assert!(minitel.output.is_empty());
+        }
+        let mut minitel = MockPort::new(&[]);
+        let err = minitel
+            .fill_row(25, '-', C1::CharWhite, C1::BgBlack)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn write_g1_bitmap() {
+        let mut minitel = MockPort::new(&[]);
+        let data = vec![
+            vec![G1(0x7F), G1(0x7F), G1(0x7F), G1(0x20)],
+            vec![G1(0x21), G1(0x22)],
+        ];
+        minitel
+            .write_g1_bitmap(4, 2, &data, C1::CharWhite, C1::BgBlue)
+            .await
+            .unwrap();
+        assert_eq!(
+            minitel.output,
+            vec![
+                0x1F, 0x42, 0x45, 0x0E, 0x1B, 0x47, 0x1B, 0x54, // row 2, G1, colors
+                0x7F, 0x12, 0x42, 0x20, // full block 3 times, empty
+                0x1F, 0x43, 0x45, 0x0E, 0x1B, 0x47, 0x1B, 0x54, // row 3, G1, colors
+                0x21, 0x22,
+            ]
+        );
+
+        // Wider than the screen, instead of a truncated repetition
+        let data = vec![vec![G1(0x7F); 300]];
+        let mut minitel = MockPort::new(&[]);
+        let err = minitel
+            .write_g1_bitmap(0, 1, &data, C1::CharWhite, C1::BgBlue)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(minitel.output.is_empty());
+
+        let data = vec![vec![G1(0x7F)]; 2];
+        let err = minitel
+            .write_g1_bitmap(0, 24, &data, C1::CharWhite, C1::BgBlue)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn write_colored_canvas() {
+        let mut canvas = ColoredCanvas::new(3, 1, C1::CharWhite, C1::BgBlack);
+        canvas.set_pixel(2, 0, true);
+        canvas.set_pixel_color(3, 2, C1::CharRed, C1::BgBlack);
+        canvas.set_pixel_color(4, 0, C1::CharRed, C1::BgBlack);
+        let mut minitel = MockPort::new(&[]);
+        minitel.write_colored_canvas(0, 1, &canvas).await.unwrap();
+        assert_eq!(
+            minitel.output,
+            vec![
+                0x1F, 0x41, 0x41, 0x0E, 0x20, // row 1, G1, blank with default colors
+                0x1B, 0x41, 0x21, 0x20, // red, top left pixel, blank
+            ]
+        );
+
+        for (x, y, canvas) in [
+            (38, 1, &canvas),
+            (
+                0,
+                1,
+                &ColoredCanvas::new(255, 1, C1::CharWhite, C1::BgBlack),
+            ),
+            (
+                0,
+                250,
+                &ColoredCanvas::new(1, 10, C1::CharWhite, C1::BgBlack),
+            ),
+        ] {
+            let mut minitel = MockPort::new(&[]);
+            let err = minitel
+                .write_colored_canvas(x, y, canvas)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+            assert!(minitel.output.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn writing_context() {
+        let mut minitel = MockPort::new(&[]);
+        let mut context = minitel.writing_context();
+        context.set_pos(30, 1).await.unwrap();
+        context.write_str("Bonjour").await.unwrap();
+        assert_eq!(context.current_col(), 37);
+        context.write_repeat(3).await.unwrap();
+        assert_eq!(context.current_col(), 0);
+        context.write_str_truncated("Minitel", 4).await.unwrap();
+        assert_eq!(context.current_col(), 4);
+
+        let mut minitel = MockPort::new(&[]);
+        let mut context = minitel.writing_context();
+        context.set_pos(32, 1).await.unwrap();
+        context.write_str_wrapped("le minitel").await.unwrap();
+        assert_eq!(context.current_col(), 7);
+        assert_eq!(minitel.output[3..], *b"le\r\nminitel");
+
+        // The first word fills the row, the next one starts at column 0 without a space
+        let mut minitel = MockPort::new(&[]);
+        let mut context = minitel.writing_context();
+        let first = "a".repeat(40);
+        context
+            .write_str_wrapped(&format!("{first} minitel"))
+            .await
+            .unwrap();
+        assert_eq!(context.current_col(), 7);
+        assert_eq!(minitel.output, format!("{first}minitel").as_bytes());
+    }
+
+    #[tokio::test]
+    async fn wait_for_n() {
+        let mut minitel = MockPort::new(&[0x00, 0x00, 0x01, 0x02]);
+        minitel.wait_for_n(C0::SOH, 3).await.unwrap();
+        assert_eq!(minitel.input, vec![0x02]);
+
+        let mut minitel = MockPort::new(&[0x00, 0x00, 0x01]);
+        let err = minitel.wait_for_n(C0::SOH, 2).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert_eq!(
+            err.to_string(),
+            "Unexpected byte 0x00 instead of 0x01 while waiting for 0x01"
+        );
+
+        // No ESC before the PRO2 answer
+        let mut minitel = MockPort::new(&[0x00; 10]);
+        let err = minitel
+            .read_pro2(Pro2Resp::QuerySpeedAnswer)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unexpected byte 0x00 instead of 0x1B while waiting for ESC"
+        );
+    }
+
+    #[tokio::test]
+    async fn clear() {
+        let mut minitel = MockPort::new(&[]);
+        minitel.clear_screen().await.unwrap();
+        minitel.clear_line_at(3).await.unwrap();
+        minitel.clear_to_end_of_screen().await.unwrap();
+        assert_eq!(
+            minitel.output,
+            vec![
+                0x0C, // FF
+                0x1F, 0x43, 0x41, 0x1B, 0x5B, 0x32, 0x4B, // row 3, CSI 2 K
+                0x1B, 0x5B, 0x4A, // CSI J
+            ]
+        );
+
+        let mut minitel = MockPort::new(&[]);
+        let err = minitel.clear_line_at(25).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn write_numbers() {
+        let mut minitel = MockPort::new(&[]);
+        minitel.write_uint(0).await.unwrap();
+        minitel.write_uint(u32::MAX).await.unwrap();
+        minitel.write_int(-42).await.unwrap();
+        minitel.write_int(i32::MIN).await.unwrap();
+        minitel.write_int(7).await.unwrap();
+        assert_eq!(minitel.output, b"04294967295-42-21474836487");
+    }
+
+    #[tokio::test]
+    async fn write_str_lossy() {
+        let mut minitel = MockPort::new(&[]);
+        assert_eq!(minitel.write_str_lossy("a☃b☂").await.unwrap(), 2);
+        assert_eq!(minitel.output, b"a?b?");
+
+        let mut minitel = MockPort::new(&[]);
+        assert_eq!(minitel.write_str_lossy_with("a☃", '_').await.unwrap(), 1);
+        assert_eq!(minitel.output, b"a_");
+
+        let mut minitel = MockPort::new(&[]);
+        let err = minitel.write_str_lossy_with("a☃", '☂').await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(minitel.output.is_empty());
+    }
+
+    #[tokio::test]
+    async fn write_at() {
+        let mut minitel = MockPort::new(&[]);
+        minitel.write_at(2, 3, "Hé").await.unwrap();
+        assert_eq!(minitel.output, b"\x1F\x43\x43H\x19\x42e");
+
+        let err = minitel.write_at(0, 25, "Hé").await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(minitel.output.len(), 7);
+    }
+
+    #[tokio::test]
+    async fn cursor_home() {
+        let mut minitel = MockPort::new(&[]);
+        minitel.cursor_home().await.unwrap();
+        minitel.cursor_start_of_line().await.unwrap();
+        assert_eq!(minitel.output, vec![0x1E, 0x0D]);
+    }
+
+    #[tokio::test]
+    async fn close() {
+        let mut minitel = MockPort::new(&[]);
+        minitel.close().await.unwrap();
+        assert_eq!(minitel.output, vec![0x0C, 0x11]);
+    }
+
+    #[tokio::test]
+    async fn send_raw_bytes() {
+        let mut minitel = MockPort::new(&[]);
+        let raw = vec![0x1B, 0x39, 0x7B];
+        minitel.send(raw.as_slice()).await.unwrap();
+        minitel.send(raw).await.unwrap();
+        minitel.send([0x0C]).await.unwrap();
+        minitel.send(b"AB").await.unwrap();
+        assert_eq!(
+            minitel.output,
+            vec![0x1B, 0x39, 0x7B, 0x1B, 0x39, 0x7B, 0x0C, b'A', b'B']
+        );
+    }
+
     #[tokio::test]
     async fn write_str() {
         let seq: Vec<u8> = Vec::new();
@@ -344,5 +2213,15 @@ mod tests {
             .unwrap();
         let written = minitel.into_inner();
         assert_eq!(written, vec![0x48, 0x19, 0x42, 0x65, 0x19, 0x3D]); // H, SS2, ', e, SS2, ½
+
+        let mut minitel = MockPort::new(&[]);
+        minitel.write_str("Hé½☃").await.unwrap();
+        assert_eq!(minitel.output, written);
+
+        // Longer than the stack buffer
+        let text = "é".repeat(100);
+        let mut minitel = MockPort::new(&[]);
+        minitel.write_str(&text).await.unwrap();
+        assert_eq!(minitel.output, StringMessage(text).message());
     }
 }
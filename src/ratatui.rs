@@ -1,19 +1,29 @@
-use std::io::Write;
+use std::{
+    io::{Error, ErrorKind, Read, Write},
+    sync::Arc,
+};
 
 use backend::WindowSize;
 
 use ratatui::prelude::*;
 use ratatui::style::Styled;
-use ratatui::{backend::Backend, buffer::Cell};
+use ratatui::{
+    backend::{Backend, ClearType},
+    buffer::Cell,
+};
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    stum::videotex::{GrayScale, Repeat, SIChar, SetPosition, C0, C1, G0, G1},
+    stum::videotex::{
+        ColorPair, CsiSequence, GrayScale, Repeat, SIChar, SetPosition, C0, C1, G0, G1,
+    },
     MinitelMessage,
 };
 
 /// Keep track of the contextual data
 ///
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CharKind {
     None,
     /// Last char was a normal char
@@ -32,16 +42,87 @@ impl CharKind {
     }
 }
 
+/// Rendering of the bold modifier, which has no direct minitel equivalent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BoldStrategy {
+    /// Double width characters. The right half of a bold character covers the next cell,
+    /// which is not drawn.
+    DoubleWidth,
+    /// Next brighter foreground color, on the grayscale of a black and white minitel
+    BrightColor,
+    /// Bold is not rendered
+    #[default]
+    Ignore,
+}
+
+/// Screen state tracked by the backend, for debugging and screenshots
+///
+/// Obtained with [`MinitelBackend::render_state`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RenderState {
+    pub cursor_position: (u16, u16),
+    pub last_char_kind: CharKind,
+    /// Colors set on the minitel, None when unknown
+    pub colors: Option<ColorPair>,
+    pub char_attributes: Vec<C1>,
+    pub zone_attributes: Vec<C1>,
+    pub bold_strategy: BoldStrategy,
+    pub row_offset: u16,
+    pub cursor_visible: bool,
+}
+
+impl RenderState {
+    /// Whether the current zone has an inverted background
+    ///
+    /// The backend only sends `InvertBg` or `NormalBg` when this changes.
+    pub fn is_inverted(&self) -> bool {
+        self.zone_attributes.contains(&C1::InvertBg)
+    }
+}
+
+/// Rendering function of a widget
+type RenderFn = Arc<dyn Fn(Rect, &mut Buffer) + Send + Sync>;
+
+/// Widget rendered on the status line, with its last rendered content
+#[derive(Clone)]
+struct StatusLine {
+    render: RenderFn,
+    last: Buffer,
+}
+
 /// Ratatui minitel backend
+///
+/// The backend writes the rendered frames to its stream. The stream stays reachable
+/// through the terminal with `terminal.backend_mut().stream_mut()`, to flush the frames
+/// or to read the user input.
+///
+/// Cloning the backend also clones the tracked screen state along with the stream.
+#[derive(Clone)]
 pub struct MinitelBackend<S: Write> {
     pub stream: S,
 
     cursor_position: (u16, u16),
     last_char_kind: CharKind,
+    /// Colors set on the minitel, None when unknown
+    colors: Option<ColorPair>,
     char_attributes: Vec<C1>,
     zone_attributes: Vec<C1>,
     repeat: u8,
     last_cell: Option<Cell>,
+    bold_strategy: BoldStrategy,
+    /// Cell hidden by the last double width char
+    covered_cell: Option<(u16, u16)>,
+    /// First minitel row used by ratatui, 1 when the status line is reserved
+    row_offset: u16,
+    /// Cursor visibility, the minitel starts with a hidden cursor
+    cursor_visible: bool,
+    /// Widget rendered on the status line at each flush
+    status_line: Option<StatusLine>,
+    /// Last cells drawn with an invalidation group, by row and column
+    #[cfg(feature = "invalidation-group")]
+    grouped_cells: std::collections::BTreeMap<(u16, u16), Cell>,
 }
 
 impl<S: Write> MinitelBackend<S> {
@@ -51,10 +132,74 @@ impl<S: Write> MinitelBackend<S> {
             cursor_position: (255, 255),
             last_char_kind: CharKind::None,
 
+            colors: None,
             char_attributes: Vec::new(),
             zone_attributes: Vec::new(),
             repeat: 0,
             last_cell: None,
+            bold_strategy: BoldStrategy::default(),
+            covered_cell: None,
+            row_offset: 0,
+            cursor_visible: false,
+            status_line: None,
+            #[cfg(feature = "invalidation-group")]
+            grouped_cells: std::collections::BTreeMap::new(),
+        }
+    }
+
+    /// Backend leaving the status line (row 0) out of ratatui
+    ///
+    /// ratatui sees a 40×24 screen, its row 0 being the first row of the minitel screen.
+    /// The status line is left for the application, and is not cleared by `clear()`.
+    pub fn new_standard_mode(stream: S) -> Self {
+        Self {
+            row_offset: 1,
+            ..Self::new(stream)
+        }
+    }
+
+    /// Render a widget on the status line (row 0) at each flush
+    ///
+    /// The status line is left out of ratatui, as with [`Self::new_standard_mode`].
+    /// Only the cells that changed since the previous flush are sent.
+    pub fn with_status_line<W>(self, widget: W) -> Self
+    where
+        W: Widget + Clone + Send + Sync + 'static,
+    {
+        Self {
+            row_offset: 1,
+            status_line: Some(StatusLine {
+                render: Arc::new(move |area, buf| widget.clone().render(area, buf)),
+                last: Buffer::empty(Rect::new(0, 0, 40, 1)),
+            }),
+            ..self
+        }
+    }
+
+    /// Select how the bold modifier is rendered
+    pub fn with_bold_strategy(self, bold_strategy: BoldStrategy) -> Self {
+        Self {
+            bold_strategy,
+            ..self
+        }
+    }
+
+    /// Stream receiving the rendered frames
+    pub fn stream_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Snapshot of the tracked screen state
+    pub fn render_state(&self) -> RenderState {
+        RenderState {
+            cursor_position: self.cursor_position,
+            last_char_kind: self.last_char_kind,
+            colors: self.colors,
+            char_attributes: self.char_attributes.clone(),
+            zone_attributes: self.zone_attributes.clone(),
+            bold_strategy: self.bold_strategy,
+            row_offset: self.row_offset,
+            cursor_visible: self.cursor_visible,
         }
     }
 
@@ -64,19 +209,33 @@ impl<S: Write> MinitelBackend<S> {
     {
         self.stream.write_all(&message.message())
     }
-}
 
-impl<S: Write> Backend for MinitelBackend<S> {
-    #[inline(always)]
-    fn draw<'a, I>(&mut self, content: I) -> std::io::Result<()>
-    where
-        I: Iterator<Item = (u16, u16, &'a Cell)>,
-    {
+    /// Send the cells to the minitel, following the tracked state
+    fn draw_cells<'a>(
+        &mut self,
+        content: impl Iterator<Item = (u16, u16, &'a Cell)>,
+    ) -> std::io::Result<()> {
         for (x, y, cell) in content {
+            // Cells hidden by a wide char have an empty symbol
+            if cell.symbol().is_empty() {
+                continue;
+            }
+
             self.cursor_position.0 += 1;
 
+            // Skip the cell covered by a double width char
+            if self.covered_cell.take() == Some((x, y)) {
+                continue;
+            }
+
+            let width = cell.symbol().width() as u16;
+            let bold = cell.modifier.contains(Modifier::BOLD);
+            let double_width = bold && self.bold_strategy == BoldStrategy::DoubleWidth;
+
             // Check if the cell is a repeat
             if (self.cursor_position.0, self.cursor_position.1) == (x, y)
+                && !double_width
+                && width == 1
                 && Some(cell.to_owned()) == self.last_cell
             {
                 self.repeat += 1;
@@ -87,57 +246,31 @@ impl<S: Write> Backend for MinitelBackend<S> {
             }
             self.last_cell = Some(cell.to_owned());
 
-            // Zone attributes: background color, invert, ...
-            let mut zone_attributes = vec![match cell.bg {
-                Color::Black => C1::BgBlack,
-                Color::Red => C1::BgRed,
-                Color::Green => C1::BgGreen,
-                Color::Yellow => C1::BgYellow,
-                Color::Blue => C1::BgBlue,
-                Color::Magenta => C1::BgMagenta,
-                Color::Cyan => C1::BgCyan,
-                Color::Gray => GrayScale::Gray50.char(),
-                Color::DarkGray => GrayScale::Gray40.char(),
-                Color::LightRed => C1::BgRed,
-                Color::LightGreen => C1::BgGreen,
-                Color::LightYellow => C1::BgYellow,
-                Color::LightBlue => C1::BgBlue,
-                Color::LightMagenta => C1::BgMagenta,
-                Color::LightCyan => C1::BgCyan,
-                Color::White => C1::BgWhite,
-                _ => C1::BgBlack,
-            }];
-            zone_attributes.push(match cell.modifier.contains(Modifier::UNDERLINED) {
-                true => C1::BeginUnderline,
-                false => C1::EndUnderline,
-            });
-            zone_attributes.push(match cell.modifier.contains(Modifier::REVERSED) {
-                true => C1::InvertBg,
-                false => C1::NormalBg,
-            });
-
-            // Char attributes: foreground color, blink, ...
-            let mut char_attributes = Vec::new();
-            char_attributes.push(match cell.fg {
-                Color::Black => C1::CharBlack,
-                Color::Red => C1::CharRed,
-                Color::Green => C1::CharGreen,
-                Color::Yellow => C1::CharYellow,
-                Color::Blue => C1::CharBlue,
-                Color::Magenta => C1::CharMagenta,
-                Color::Cyan => C1::CharCyan,
-                Color::Gray => GrayScale::Gray50.char(),
-                Color::DarkGray => GrayScale::Gray40.char(),
-                Color::LightRed => C1::CharRed,
-                Color::LightGreen => C1::CharGreen,
-                Color::LightYellow => C1::CharYellow,
-                Color::LightBlue => C1::CharBlue,
-                Color::LightMagenta => C1::CharMagenta,
-                Color::LightCyan => C1::CharCyan,
-                Color::White => C1::CharWhite,
-                _ => C1::CharWhite,
-            });
+            let mut colors = ColorPair::new(
+                char_color(cell.fg).unwrap_or(C1::CharWhite),
+                char_color(cell.bg)
+                    .and_then(|color| color.corresponding_bg())
+                    .unwrap_or(C1::BgBlack),
+            );
+
+            if bold && self.bold_strategy == BoldStrategy::BrightColor {
+                colors.fg = brighter(colors.fg);
+            }
 
+            // Zone attributes: underline, invert, ...
+            let zone_attributes = vec![
+                match cell.modifier.contains(Modifier::UNDERLINED) {
+                    true => C1::BeginUnderline,
+                    false => C1::EndUnderline,
+                },
+                match cell.modifier.contains(Modifier::REVERSED) {
+                    true => C1::InvertBg,
+                    false => C1::NormalBg,
+                },
+            ];
+
+            // Char attributes: blink, ...
+            let mut char_attributes = Vec::new();
             if cell.modifier.contains(Modifier::RAPID_BLINK)
                 || cell.modifier.contains(Modifier::SLOW_BLINK)
             {
@@ -148,7 +281,7 @@ impl<S: Write> Backend for MinitelBackend<S> {
 
             // Chose between a char or a semi graphic
             // The crossed out modifier is taken as prefering a semi graphic char
-            let c = cell.symbol().chars().next().unwrap();
+            let c = cell.symbol().chars().next().unwrap_or(' ');
             let char_kind = if cell.modifier.contains(Modifier::CROSSED_OUT) {
                 G1::approximate_char(c)
                     .map(CharKind::SemiGraphic)
@@ -167,26 +300,41 @@ impl<S: Write> Backend for MinitelBackend<S> {
                     })
             };
 
+            let double_width = double_width
+                && matches!(char_kind, CharKind::Alphabet(c) if c != SIChar::G0(G0(0x20)));
+            if self.bold_strategy == BoldStrategy::DoubleWidth {
+                char_attributes.push(match double_width {
+                    true => C1::DoubleWidth,
+                    false => C1::NormalSize,
+                });
+            }
+
             // Check if the previous context is invalidated
             if self.cursor_position != (x, y)
                 || std::mem::discriminant(&self.last_char_kind)
                     != std::mem::discriminant(&char_kind)
             {
                 self.cursor_position = (x, y);
+                self.colors = None;
                 self.char_attributes = Vec::new();
                 self.zone_attributes = Vec::new();
                 self.last_char_kind = char_kind;
 
                 // Move the cursor to the right position, select the char set
                 self.stream
-                    .write_all(&SetPosition(x as u8, y as u8).message())?;
+                    .write_all(&SetPosition(x as u8, (y + self.row_offset) as u8).message())?;
 
                 self.send(char_kind.escape_code())?;
             }
 
             match char_kind {
                 CharKind::Alphabet(SIChar::G0(G0(0x20))) => {
-                    // Empty char, update the zone attributes if necessary
+                    // Empty char, update the background and zone attributes if necessary
+                    let colors = ColorPair {
+                        fg: self.colors.map_or(colors.fg, |c| c.fg),
+                        ..colors
+                    };
+                    self.update_colors(colors)?;
                     if self.zone_attributes != zone_attributes {
                         for attr in &zone_attributes {
                             self.send(*attr)?;
@@ -196,7 +344,12 @@ impl<S: Write> Backend for MinitelBackend<S> {
                     self.send(SIChar::G0(G0(0x20)))?;
                 }
                 CharKind::Alphabet(c) => {
-                    // Alphabetic char, update the char attributes if necessary
+                    // Alphabetic char, update the foreground and char attributes if necessary
+                    let colors = ColorPair {
+                        bg: self.colors.map_or(colors.bg, |c| c.bg),
+                        ..colors
+                    };
+                    self.update_colors(colors)?;
                     if self.char_attributes != char_attributes {
                         for attr in &char_attributes {
                             self.send(*attr)?;
@@ -204,9 +357,13 @@ impl<S: Write> Backend for MinitelBackend<S> {
                         self.char_attributes.clone_from(&char_attributes);
                     }
                     self.send(c)?;
+                    if double_width {
+                        self.covered_cell = Some((x + 1, y));
+                    }
                 }
                 CharKind::SemiGraphic(c) => {
                     // Semigraphic char, update both the zone and char attributes if necessary
+                    self.update_colors(colors)?;
                     if self.zone_attributes != zone_attributes {
                         for attr in &zone_attributes {
                             self.send(*attr)?;
@@ -222,7 +379,16 @@ impl<S: Write> Backend for MinitelBackend<S> {
                     // Write the semi graphic char
                     self.send(c)?;
                 }
-                _ => {}
+                CharKind::None => {
+                    // Nothing was written, the minitel cursor did not move
+                    self.cursor_position = (255, 255);
+                }
+            }
+
+            // Pad wide chars to keep the minitel cursor in sync with the ratatui cells
+            if width > 1 && char_kind != CharKind::None {
+                self.stream.write_all(&vec![0x20; (width - 1) as usize])?;
+                self.cursor_position.0 += width - 1;
             }
         }
         if self.repeat > 0 {
@@ -232,16 +398,190 @@ impl<S: Write> Backend for MinitelBackend<S> {
         Ok(())
     }
 
+    /// Add the unchanged cells of the invalidation groups touched by the content
+    ///
+    /// The cells are returned in row-major order.
+    #[cfg(feature = "invalidation-group")]
+    fn invalidate_groups<'a>(
+        &mut self,
+        content: impl Iterator<Item = (u16, u16, &'a Cell)>,
+    ) -> Vec<(u16, u16, Cell)> {
+        let mut cells: Vec<(u16, u16, Cell)> =
+            content.map(|(x, y, cell)| (x, y, cell.clone())).collect();
+
+        // Update the known groups, keeping track of the invalidated ones
+        let mut invalidated = std::collections::HashSet::new();
+        for (x, y, cell) in &cells {
+            let previous = match cell.underline_color {
+                Color::Indexed(group) => {
+                    invalidated.insert(group);
+                    self.grouped_cells.insert((*y, *x), cell.clone())
+                }
+                _ => self.grouped_cells.remove(&(*y, *x)),
+            };
+            if let Some(Cell {
+                underline_color: Color::Indexed(group),
+                ..
+            }) = previous
+            {
+                invalidated.insert(group);
+            }
+        }
+
+        // Re-send the other cells of the invalidated groups
+        let changed: std::collections::HashSet<(u16, u16)> =
+            cells.iter().map(|(x, y, _)| (*x, *y)).collect();
+        for (&(y, x), cell) in &self.grouped_cells {
+            if matches!(cell.underline_color, Color::Indexed(group) if invalidated.contains(&group))
+                && !changed.contains(&(x, y))
+            {
+                cells.push((x, y, cell.clone()));
+            }
+        }
+        cells.sort_by_key(|(x, y, _)| (*y, *x));
+        cells
+    }
+
+    /// Render the status line widget, and send the cells that changed
+    fn draw_status_line(&mut self) -> std::io::Result<()> {
+        let Some(status_line) = self.status_line.take() else {
+            return Ok(());
+        };
+        let mut buffer = Buffer::empty(status_line.last.area);
+        (status_line.render)(buffer.area, &mut buffer);
+
+        // The status line is drawn out of the ratatui coordinates, the tracked cursor
+        // position is meaningless before and after
+        self.cursor_position = (255, 255);
+        let row_offset = std::mem::replace(&mut self.row_offset, 0);
+        let result = self.draw_cells(status_line.last.diff(&buffer).into_iter());
+        self.row_offset = row_offset;
+        self.cursor_position = (255, 255);
+
+        self.status_line = Some(StatusLine {
+            last: buffer,
+            ..status_line
+        });
+        result
+    }
+
+    /// Send the color attributes that differ from the current ones
+    fn update_colors(&mut self, colors: ColorPair) -> std::io::Result<()> {
+        let attributes = match self.colors {
+            Some(current) => ColorPair::diff(&current, &colors),
+            None => colors.into(),
+        };
+        for attr in attributes {
+            self.send(attr)?;
+        }
+        self.colors = Some(colors);
+        Ok(())
+    }
+}
+
+impl<S: Write + Read> MinitelBackend<S> {
+    /// Ask the minitel for its cursor position
+    ///
+    /// Slower than `get_cursor_position`, which returns the tracked position, but
+    /// reliable after writes that bypassed the backend.
+    pub fn query_cursor_hardware(&mut self) -> std::io::Result<Position> {
+        self.send(C1::EnqCursor)?;
+        self.stream.flush()?;
+        let mut byte = [0];
+        for _ in 0..10 {
+            self.stream.read_exact(&mut byte)?;
+            if C0::from(byte[0]) == C0::US {
+                let mut position = [0; 2];
+                self.stream.read_exact(&mut position)?;
+                let y = (position[0] as u16)
+                    .checked_sub(0x40 + self.row_offset)
+                    .ok_or(Error::from(ErrorKind::InvalidData))?;
+                let x = (position[1] as u16)
+                    .checked_sub(0x41)
+                    .ok_or(Error::from(ErrorKind::InvalidData))?;
+                return Ok(Position::new(x, y));
+            }
+        }
+        Err(ErrorKind::TimedOut.into())
+    }
+}
+
+/// Backend for the async minitel ports, such as the axum websocket or the ESP32 serial port
+///
+/// Ratatui draws synchronously in the buffer of the backend, which is then sent
+/// with [`MinitelBackend::flush_to`] after each frame.
+pub type AsyncMinitelBackend = MinitelBackend<Vec<u8>>;
+
+impl MinitelBackend<Vec<u8>> {
+    /// Backend drawing in a buffer, see [`AsyncMinitelBackend`]
+    pub fn buffered() -> Self {
+        Self::new(Vec::new())
+    }
+
+    /// Send the frames drawn since the last call to the minitel
+    pub async fn flush_to<W: crate::AsyncMinitelWrite>(
+        &mut self,
+        minitel: &mut W,
+    ) -> std::io::Result<()> {
+        if !self.stream.is_empty() {
+            minitel.write(&self.stream).await?;
+            self.stream.clear();
+        }
+        minitel.flush().await
+    }
+}
+
+impl<S: Write> Backend for MinitelBackend<S> {
+    #[inline(always)]
+    fn draw<'a, I>(&mut self, content: I) -> std::io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        #[cfg(feature = "invalidation-group")]
+        {
+            let cells = self.invalidate_groups(content);
+            self.draw_cells(cells.iter().map(|(x, y, cell)| (*x, *y, cell)))
+        }
+        #[cfg(not(feature = "invalidation-group"))]
+        self.draw_cells(content)
+    }
+
+    /// Scroll the screen up by n lines, by deleting the first rows
+    ///
+    /// The rows 1 to 24 are scrolled, the status line is left untouched. Unlike the rouleau
+    /// mode, no protocol exchange is needed: there is no acknowledgement to read back.
+    fn append_lines(&mut self, n: u16) -> std::io::Result<()> {
+        if n == 0 {
+            return Ok(());
+        }
+        let mut message = SetPosition(0, 1).message();
+        message.extend(CsiSequence::DeleteLines(n.min(24) as u8).message());
+        self.stream.write_all(&message)?;
+        // The cursor is left on the first row, the next draw positions it again
+        self.cursor_position = (255, 255);
+        Ok(())
+    }
+
+    /// The minitel cursor has a single shape, only its visibility can be controlled.
+    ///
+    /// ratatui 0.29 has no cursor shape in its backend trait, `Terminal` only calls
+    /// `hide_cursor` and `show_cursor`.
     fn hide_cursor(&mut self) -> std::io::Result<()> {
         self.send(C0::Coff)?;
+        self.cursor_visible = false;
         Ok(())
     }
 
     fn show_cursor(&mut self) -> std::io::Result<()> {
         self.send(C0::Con)?;
+        self.cursor_visible = true;
         Ok(())
     }
 
+    /// Position of the last char drawn, as tracked by the backend
+    ///
+    /// The minitel is not queried: the value diverges after writes that bypassed the backend,
+    /// and is out of the screen before the first draw. See `query_cursor_hardware`.
     fn get_cursor_position(&mut self) -> std::io::Result<ratatui::prelude::Position> {
         Ok(self.cursor_position.into())
     }
@@ -251,17 +591,33 @@ impl<S: Write> Backend for MinitelBackend<S> {
         position: P,
     ) -> std::io::Result<()> {
         let position: Position = position.into();
-        self.send(SetPosition(position.x as u8, position.y as u8))?;
+        self.send(SetPosition(
+            position.x as u8,
+            (position.y + self.row_offset) as u8,
+        ))?;
         Ok(())
     }
 
+    /// Clear the screen, rows 1 to 24. The status line is left untouched.
     fn clear(&mut self) -> std::io::Result<()> {
+        #[cfg(feature = "invalidation-group")]
+        self.grouped_cells.clear();
         self.send(C0::FF)?;
         Ok(())
     }
 
+    fn clear_region(&mut self, clear_type: ClearType) -> std::io::Result<()> {
+        match clear_type {
+            ClearType::All => self.clear(),
+            ClearType::AfterCursor => self.send(CsiSequence::EraseToEndOfScreen),
+            ClearType::BeforeCursor => self.send(CsiSequence::EraseFromStartOfScreen),
+            ClearType::CurrentLine => self.send(CsiSequence::EraseLine),
+            ClearType::UntilNewLine => self.send(C0::CAN),
+        }
+    }
+
     fn size(&self) -> std::io::Result<ratatui::prelude::Size> {
-        Ok(Size::new(40, 25))
+        Ok(Size::new(40, 25 - self.row_offset))
     }
 
     fn window_size(&mut self) -> std::io::Result<ratatui::backend::WindowSize> {
@@ -272,7 +628,38 @@ impl<S: Write> Backend for MinitelBackend<S> {
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        Ok(())
+        self.draw_status_line()
+    }
+}
+
+/// Foreground attribute of a ratatui color, `None` for the default colors
+fn char_color(color: Color) -> Option<C1> {
+    Some(match color {
+        Color::Black => C1::CharBlack,
+        Color::Red | Color::LightRed => C1::CharRed,
+        Color::Green | Color::LightGreen => C1::CharGreen,
+        Color::Yellow | Color::LightYellow => C1::CharYellow,
+        Color::Blue | Color::LightBlue => C1::CharBlue,
+        Color::Magenta | Color::LightMagenta => C1::CharMagenta,
+        Color::Cyan | Color::LightCyan => C1::CharCyan,
+        Color::Gray => GrayScale::Gray50.char(),
+        Color::DarkGray => GrayScale::Gray40.char(),
+        Color::White => C1::CharWhite,
+        _ => return None,
+    })
+}
+
+/// Next brighter foreground color on the grayscale
+fn brighter(fg: C1) -> C1 {
+    match fg {
+        C1::CharBlack => C1::CharBlue,
+        C1::CharBlue => C1::CharRed,
+        C1::CharRed => C1::CharMagenta,
+        C1::CharMagenta => C1::CharGreen,
+        C1::CharGreen => C1::CharCyan,
+        C1::CharCyan => C1::CharYellow,
+        C1::CharYellow => C1::CharWhite,
+        fg => fg,
     }
 }
 
@@ -302,10 +689,39 @@ pub mod border {
         horizontal_top: "▔",
         horizontal_bottom: "▁",
     };
+
+    /// One sextant pixel wide border, on the outer edge of the cells.
+    pub const SEXTANT_THIN: border::Set = border::Set {
+        top_left: "\u{1FB15}",
+        top_right: "\u{1FB28}",
+        bottom_left: "\u{1FB32}",
+        bottom_right: "\u{1FB37}",
+        vertical_left: "▌",
+        vertical_right: "▐",
+        horizontal_top: "\u{1FB02}",
+        horizontal_bottom: "\u{1FB2D}",
+    };
+
+    /// Two sextant pixels wide border, filling the vertical edges.
+    pub const SEXTANT_HEAVY: border::Set = border::Set {
+        top_left: "█",
+        top_right: "█",
+        bottom_left: "█",
+        bottom_right: "█",
+        vertical_left: "█",
+        vertical_right: "█",
+        horizontal_top: "\u{1FB0E}",
+        horizontal_bottom: "\u{1FB39}",
+    };
 }
 
 pub trait StyledMinitelExt {
     type Item;
+    /// Put the styled element in an invalidation group
+    ///
+    /// When a cell of a group changes, the backend re-sends all the cells of the group.
+    /// This keeps the zone attributes, such as the background color, applied to the
+    /// whole zone. The group is stored as the underline color.
     #[cfg(feature = "invalidation-group")]
     fn invalidation_group(self, group: u8) -> Self::Item;
 }
@@ -325,8 +741,12 @@ where
 pub mod widgets {
     use ratatui::{prelude::*, style::Styled};
 
+    use crate::stum::videotex::{FunctionKey, UserInput, G1};
+
     pub struct Fill {
         pub char: char,
+        /// Characters cycled through in row-major order, replacing `char` when not empty
+        pub pattern: Vec<char>,
         pub style: Style,
     }
 
@@ -334,6 +754,7 @@ pub mod widgets {
         fn default() -> Self {
             Self {
                 char: '█',
+                pattern: Vec::new(),
                 style: Style::default(),
             }
         }
@@ -343,6 +764,22 @@ pub mod widgets {
         pub fn with_char(self, char: char) -> Self {
             Self { char, ..self }
         }
+
+        /// Fill the area by cycling through the characters, e.g. `['▘', '▝', '▖', '▗']`
+        pub fn with_pattern(self, chars: &[char]) -> Self {
+            Self {
+                pattern: chars.to_vec(),
+                ..self
+            }
+        }
+
+        /// Fill the area by cycling through the semi graphic characters
+        pub fn with_g1_pattern(self, pattern: &[G1]) -> Self {
+            Self {
+                pattern: pattern.iter().map(G1::to_unicode).collect(),
+                ..self
+            }
+        }
     }
 
     impl Styled for Fill {
@@ -365,11 +802,408 @@ pub mod widgets {
             buf.set_style(area, self.style);
             for y in area.top()..area.bottom() {
                 for x in area.left()..area.right() {
+                    let char = match self.pattern.len() {
+                        0 => self.char,
+                        len => {
+                            let index = (y - area.top()) as usize * area.width as usize
+                                + (x - area.left()) as usize;
+                            self.pattern[index % len]
+                        }
+                    };
                     if let Some(cell) = buf.cell_mut((x, y)) {
-                        cell.set_symbol(&self.char.to_string());
+                        cell.set_symbol(&char.to_string());
                     }
                 }
             }
         }
     }
+
+    /// Event emitted by a [`NumberInput`]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum NumberInputEvent {
+        /// The user validated the value with Envoi
+        Confirmed(i64),
+    }
+
+    /// Numeric input field, rendered right-aligned and underlined
+    ///
+    /// Digits are appended to the value, Correction deletes the last digit, Annulation clears
+    /// the field and Envoi validates it.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct NumberInput {
+        pub value: Option<i64>,
+        pub min: i64,
+        pub max: i64,
+        /// Number of digits and width of the field
+        pub width: u8,
+    }
+
+    impl NumberInput {
+        pub fn new(min: i64, max: i64, width: u8) -> Self {
+            Self {
+                value: None,
+                min,
+                max,
+                width,
+            }
+        }
+
+        /// Update the value from a user input
+        ///
+        /// On Envoi, the value is clamped to the min/max range and confirmed.
+        pub fn handle_input(&mut self, input: UserInput) -> Option<NumberInputEvent> {
+            match input {
+                UserInput::Char(c @ '0'..='9') => {
+                    let digit = c as i64 - '0' as i64;
+                    let digits = self.value.map_or(0, |v| v.to_string().len());
+                    if digits < self.width as usize {
+                        self.value = self
+                            .value
+                            .unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|v| v.checked_add(digit))
+                            .or(self.value);
+                    }
+                }
+                UserInput::FunctionKey(FunctionKey::Correction) => {
+                    self.value = self.value.filter(|v| *v >= 10).map(|v| v / 10);
+                }
+                UserInput::FunctionKey(FunctionKey::Annulation) => self.value = None,
+                UserInput::FunctionKey(FunctionKey::Envoi) => {
+                    let value = self.value?.clamp(self.min, self.max);
+                    self.value = Some(value);
+                    return Some(NumberInputEvent::Confirmed(value));
+                }
+                _ => {}
+            }
+            None
+        }
+    }
+
+    impl Widget for &NumberInput {
+        fn render(self, area: Rect, buf: &mut Buffer) {
+            let width = area.width.min(self.width as u16);
+            let text = self.value.map(|v| v.to_string()).unwrap_or_default();
+            buf.set_stringn(
+                area.x,
+                area.y,
+                format!("{:>width$}", text, width = width as usize),
+                width as usize,
+                Style::default().underlined(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{TerminalOptions, Viewport};
+
+    use super::*;
+
+    #[cfg(feature = "futures")]
+    #[tokio::test]
+    async fn flush_to() {
+        let mut terminal = Terminal::new(AsyncMinitelBackend::buffered()).unwrap();
+        terminal
+            .draw(|frame| frame.render_widget("a", frame.area()))
+            .unwrap();
+        let mut minitel = crate::tests::MockPort::new(&[]);
+        terminal.backend_mut().flush_to(&mut minitel).await.unwrap();
+        assert!(minitel.output.contains(&b'a'));
+        assert!(terminal.backend().stream.is_empty());
+    }
+
+    fn draw(backend: &mut MinitelBackend<Vec<u8>>, buffer: &Buffer) {
+        let content = buffer.content.iter().enumerate().map(|(i, cell)| {
+            let (x, y) = buffer.pos_of(i);
+            (x, y, cell)
+        });
+        backend.draw(content).unwrap();
+    }
+
+    #[test]
+    fn append_lines() {
+        let mut backend = MinitelBackend::new(Vec::new());
+        backend.append_lines(2).unwrap();
+        assert_eq!(
+            backend.stream,
+            vec![0x1F, 0x41, 0x41, 0x1B, 0x5B, 0x32, 0x4D]
+        );
+
+        // At most the 24 rows of the page
+        let mut backend = MinitelBackend::new(Vec::new());
+        backend.append_lines(30).unwrap();
+        assert_eq!(backend.stream[3..], *b"\x1B[24M");
+    }
+
+    #[test]
+    fn clear_region() {
+        let mut backend = MinitelBackend::new(Vec::new());
+        backend.clear_region(ClearType::AfterCursor).unwrap();
+        backend.clear_region(ClearType::UntilNewLine).unwrap();
+        assert_eq!(backend.stream, vec![0x1B, 0x5B, 0x4A, 0x18]);
+    }
+
+    #[test]
+    fn insert_before() {
+        // Start with the cursor on the last row
+        let mut backend = MinitelBackend::new(Vec::new());
+        draw(&mut backend, &Buffer::empty(Rect::new(0, 24, 1, 1)));
+        let mut terminal = Terminal::with_options(
+            backend,
+            TerminalOptions {
+                viewport: Viewport::Inline(1),
+            },
+        )
+        .unwrap();
+        terminal
+            .insert_before(2, |buf| buf.set_string(0, 0, "A", Style::default()))
+            .unwrap();
+        let stream = &terminal.backend().stream;
+        let scroll = [0x1F, 0x41, 0x41, 0x1B, 0x5B, 0x32, 0x4D];
+        assert!(stream.windows(scroll.len()).any(|w| w == scroll));
+    }
+
+    #[test]
+    fn wide_char() {
+        let mut buffer = Buffer::empty(Rect::new(0, 1, 4, 1));
+        buffer.set_string(0, 1, "a界b", Style::default());
+
+        let mut backend = MinitelBackend::new(Vec::new());
+        draw(&mut backend, &buffer);
+        // 界 is not written, the next cell is positioned explicitly
+        let position = SetPosition(2, 1).message();
+        assert!(backend
+            .stream
+            .windows(position.len())
+            .any(|w| w == position));
+        assert_eq!(backend.stream.last(), Some(&b'b'));
+    }
+
+    /// Stream with separate input and output
+    struct Duplex {
+        input: std::io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Duplex {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Duplex {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn query_cursor_hardware() {
+        let stream = Duplex {
+            input: std::io::Cursor::new(vec![0x1F, 0x45, 0x43]),
+            output: Vec::new(),
+        };
+        let mut backend = MinitelBackend::new_standard_mode(stream);
+        assert_eq!(
+            backend.query_cursor_hardware().unwrap(),
+            Position::new(2, 4)
+        );
+        assert_eq!(backend.stream.output, vec![0x1B, 0x61]);
+    }
+
+    #[test]
+    fn fill_pattern() {
+        let area = Rect::new(0, 0, 3, 2);
+        let mut buf = Buffer::empty(area);
+        widgets::Fill::default()
+            .with_pattern(&['▘', '▝'])
+            .render(area, &mut buf);
+        assert_eq!(buf, Buffer::with_lines(["▘▝▘", "▝▘▝"]));
+
+        let pattern = [G1(0x21), G1(0x7F), G1(0x35)];
+        widgets::Fill::default()
+            .with_g1_pattern(&pattern)
+            .render(area, &mut buf);
+        for (cell, g1) in buf.content.iter().zip(pattern.iter().cycle()) {
+            let c = cell.symbol().chars().next().unwrap();
+            assert_eq!(G1::approximate_char(c), Some(*g1));
+        }
+    }
+
+    #[test]
+    fn number_input() {
+        use crate::stum::videotex::{FunctionKey, UserInput};
+        use widgets::{NumberInput, NumberInputEvent};
+
+        let mut input = NumberInput::new(0, 500, 3);
+        for c in "1234".chars() {
+            assert_eq!(input.handle_input(UserInput::Char(c)), None);
+        }
+        assert_eq!(input.value, Some(123));
+        input.handle_input(UserInput::FunctionKey(FunctionKey::Correction));
+        assert_eq!(input.value, Some(12));
+
+        let area = Rect::new(0, 0, 4, 1);
+        let mut buf = Buffer::empty(area);
+        input.render(area, &mut buf);
+        assert_eq!(buf.cell((1, 0)).unwrap().symbol(), "1");
+        assert!(buf
+            .cell((0, 0))
+            .unwrap()
+            .modifier
+            .contains(Modifier::UNDERLINED));
+        assert_eq!(buf.cell((3, 0)).unwrap().modifier, Modifier::empty());
+
+        input.handle_input(UserInput::Char('9'));
+        assert_eq!(
+            input.handle_input(UserInput::FunctionKey(FunctionKey::Envoi)),
+            Some(NumberInputEvent::Confirmed(129))
+        );
+        input.handle_input(UserInput::Char('9'));
+        assert_eq!(
+            input.handle_input(UserInput::FunctionKey(FunctionKey::Envoi)),
+            Some(NumberInputEvent::Confirmed(129)),
+            "full field"
+        );
+        input.handle_input(UserInput::FunctionKey(FunctionKey::Annulation));
+        assert_eq!(
+            input.handle_input(UserInput::FunctionKey(FunctionKey::Envoi)),
+            None
+        );
+        for c in "999".chars() {
+            input.handle_input(UserInput::Char(c));
+        }
+        assert_eq!(
+            input.handle_input(UserInput::FunctionKey(FunctionKey::Envoi)),
+            Some(NumberInputEvent::Confirmed(500))
+        );
+    }
+
+    #[test]
+    fn standard_mode() {
+        let mut backend = MinitelBackend::new_standard_mode(Vec::new());
+        assert_eq!(backend.size().unwrap(), Size::new(40, 24));
+
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 1, 1));
+        buffer.set_string(0, 0, "A", Style::default());
+        draw(&mut backend, &buffer);
+        assert_eq!(backend.stream[..3], [0x1F, 0x41, 0x41]);
+    }
+
+    #[test]
+    fn bold_double_width() {
+        let mut buffer = Buffer::empty(Rect::new(0, 1, 4, 1));
+        buffer.set_string(0, 1, "AB", Style::default().bold());
+        buffer.set_string(2, 1, "CD", Style::default());
+
+        let mut backend =
+            MinitelBackend::new(Vec::new()).with_bold_strategy(BoldStrategy::DoubleWidth);
+        draw(&mut backend, &buffer);
+        // B is covered by the double width A
+        assert_eq!(
+            backend.stream,
+            vec![
+                0x1F, 0x41, 0x41, 0x0F, // position, G0
+                0x1B, 0x47, 0x1B, 0x50, 0x1B, 0x49, 0x1B, 0x4E,
+                b'A', // white on black, fixed, double width
+                0x1B, 0x49, 0x1B, 0x4C, b'C', // fixed, normal size
+                b'D',
+            ]
+        );
+    }
+
+    #[test]
+    fn bold_ignored_by_default() {
+        let mut buffer = Buffer::empty(Rect::new(0, 1, 2, 1));
+        buffer.set_string(0, 1, "AB", Style::default().bold());
+
+        let mut backend = MinitelBackend::new(Vec::new());
+        draw(&mut backend, &buffer);
+        assert_eq!(
+            backend.stream,
+            vec![0x1F, 0x41, 0x41, 0x0F, 0x1B, 0x47, 0x1B, 0x50, 0x1B, 0x49, b'A', b'B']
+        );
+    }
+
+    #[cfg(feature = "invalidation-group")]
+    #[test]
+    fn invalidation_group() {
+        let mut backend = MinitelBackend::new(Vec::new());
+        let mut buffer = Buffer::empty(Rect::new(0, 1, 3, 1));
+        buffer.set_string(0, 1, "AB", Style::default().invalidation_group(1));
+        buffer.set_string(2, 1, "C", Style::default());
+        draw(&mut backend, &buffer);
+
+        // Only A changed, B is sent again as part of the group
+        backend.stream.clear();
+        let cell = Cell::new("X")
+            .set_style(Style::default().invalidation_group(1))
+            .clone();
+        backend.draw([(0, 1, &cell)].into_iter()).unwrap();
+        assert!(backend.stream.ends_with(b"XB"));
+
+        // Leaving the group invalidates it too
+        backend.stream.clear();
+        let cell = Cell::new("Y");
+        backend.draw([(0, 1, &cell)].into_iter()).unwrap();
+        assert!(backend.stream.ends_with(b"B"));
+        assert!(backend.stream.contains(&b'Y'));
+
+        // Ungrouped cells are left alone
+        backend.stream.clear();
+        backend.draw([(2, 1, &Cell::new("Z"))].into_iter()).unwrap();
+        assert!(!backend.stream.contains(&b'B'));
+    }
+
+    #[test]
+    fn status_line() {
+        let mut backend = MinitelBackend::new(Vec::new()).with_status_line(Span::raw("Hi"));
+        assert_eq!(backend.size().unwrap(), Size::new(40, 24));
+        backend.flush().unwrap();
+        assert_eq!(backend.stream[..4], [0x1F, 0x40, 0x41, 0x0F]);
+        assert!(backend.stream.ends_with(b"Hi"));
+
+        // Unchanged status line
+        backend.stream.clear();
+        backend.flush().unwrap();
+        assert!(backend.stream.is_empty());
+
+        // The next draw moves the cursor back to the screen
+        let mut buffer = Buffer::empty(Rect::new(2, 0, 1, 1));
+        buffer.set_string(2, 0, "A", Style::default());
+        draw(&mut backend, &buffer);
+        assert_eq!(backend.stream[..3], [0x1F, 0x41, 0x43]);
+    }
+
+    #[test]
+    fn invert_sent_once() {
+        let mut buffer = Buffer::empty(Rect::new(0, 1, 2, 1));
+        buffer.set_string(0, 1, "  ", Style::default().reversed());
+
+        let mut backend = MinitelBackend::new(Vec::new());
+        draw(&mut backend, &buffer);
+        assert!(backend.render_state().is_inverted());
+        assert!(!backend.render_state().cursor_visible);
+        let inversions = backend.stream.windows(2).filter(|w| w == &[0x1B, 0x5D]);
+        assert_eq!(inversions.count(), 1);
+    }
+
+    #[test]
+    fn gray_background() {
+        let mut buffer = Buffer::empty(Rect::new(0, 1, 1, 1));
+        buffer.set_string(0, 1, "a", Style::default().fg(Color::Gray).bg(Color::Gray));
+
+        let mut backend = MinitelBackend::new(Vec::new());
+        draw(&mut backend, &buffer);
+        assert_eq!(
+            backend.render_state().colors,
+            Some(ColorPair::new(C1::CharRed, C1::BgRed))
+        );
+    }
 }
@@ -3,13 +3,16 @@
 //! Reference: <https://jbellue.github.io/stum1b/#2-6>
 
 use core::fmt;
-use std::fmt::{Display, Formatter};
+use std::{
+    fmt::{Display, Formatter},
+    io::{self, ErrorKind},
+};
 
 use num_enum::{FromPrimitive, IntoPrimitive};
 
 use crate::{
     stum::videotex::{self, C1},
-    MinitelMessage,
+    AsyncMinitelReadWrite, MinitelMessage,
 };
 
 /// Emission code of the Minitel modules
@@ -17,6 +20,7 @@ use crate::{
 /// <https://jbellue.github.io/stum1b/#2-6-1>
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RoutingTx {
     Screen = 0x50,
     Keyboard = 0x51,
@@ -31,6 +35,7 @@ pub enum RoutingTx {
 /// <https://jbellue.github.io/stum1b/#2-6-1>
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RoutingRx {
     Screen = 0x58,
     Keyboard = 0x59,
@@ -42,6 +47,7 @@ pub enum RoutingRx {
 
 /// Protocol messages
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ProtocolMessage {
     Pro1(Pro1),
     Pro2(Pro2, u8),
@@ -65,6 +71,7 @@ impl MinitelMessage for ProtocolMessage {
 }
 
 impl ProtocolMessage {
+    /// Enable or disable the routing from an emitter to a receiver, see [`aiguillage`]
     pub fn aiguillage(enable: bool, from: RoutingTx, to: RoutingRx) -> Self {
         ProtocolMessage::Pro3(
             if enable {
@@ -77,6 +84,7 @@ impl ProtocolMessage {
         )
     }
 
+    /// Program the speed of the prise, in both directions
     pub fn set_speed(speed: Baudrate) -> Self {
         ProtocolMessage::Pro2(Pro2::Prog, speed.code())
     }
@@ -88,6 +96,11 @@ impl ProtocolMessage {
 
 /// Sequence for a protocol message to enable or disable a routing
 ///
+/// A routing links a single emitter to a single receiver, and carries no speed.
+/// Asymmetric configurations are built with one sequence per link, for instance
+/// routing the keyboard to the modem but not to the screen. The only speed is the
+/// one of the prise, programmed with [`ProtocolMessage::set_speed`].
+///
 /// <https://jbellue.github.io/stum1b/#2-6-3>
 pub fn aiguillage(enable: bool, from: RoutingTx, to: RoutingRx) -> ProtocolMessage {
     ProtocolMessage::Pro3(
@@ -104,10 +117,19 @@ pub fn aiguillage(enable: bool, from: RoutingTx, to: RoutingRx) -> ProtocolMessa
 /// Protocol messages with one parameter
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Pro1 {
+    /// Terminal status enquiry, answered by `Pro2Resp::RepTerminalStatus`
+    EnqTerminalStatus = 0x70,
+    /// <https://jbellue.github.io/stum1b/#2-6-11>
+    EnqFunctionStatus = 0x72,
     EnqSpeed = 0x74,
+    /// Protocol status enquiry, answered by `Pro2Resp::RepProtocolStatus`
+    EnqProtocolStatus = 0x76,
     /// <https://jbellue.github.io/stum1b/#2-6-6>
     EnqRom = 0x7B,
+    /// Reset the terminal to its power-on state
+    Reset = 0x7F,
     #[num_enum(catch_all)]
     Unknown(u8),
 }
@@ -115,7 +137,11 @@ pub enum Pro1 {
 /// Protocol messages with two parameters
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Pro2 {
+    /// Query the routing status of a module. There is no PRO3 query command.
+    ///
+    /// <https://jbellue.github.io/stum1b/#2-6-4>
     RoutingTo = 0x62,
     Start = 0x69,
     Stop = 0x6A,
@@ -127,6 +153,7 @@ pub enum Pro2 {
 /// Protocol messages with three parameters
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Pro3 {
     RoutingOff = 0x60,
     RoutingOn = 0x61,
@@ -137,9 +164,12 @@ pub enum Pro3 {
 /// Protocol responses with two parameter
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Pro2Resp {
+    RepTerminalStatus = 0x71,
     RepStatus = 0x73,
     QuerySpeedAnswer = 0x75,
+    RepProtocolStatus = 0x77,
     #[num_enum(catch_all)]
     Unknown(u8),
 }
@@ -147,6 +177,7 @@ pub enum Pro2Resp {
 /// Protocol responses with three parameter
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Pro3Resp {
     RoutingFrom = 0x63,
     #[num_enum(catch_all)]
@@ -158,6 +189,7 @@ pub enum Pro3Resp {
 /// <https://jbellue.github.io/stum1b/#2-6-11>
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FunctionMode {
     /// Mode Rouleau (screen scrolling)
     Rouleau = 0x43,
@@ -169,7 +201,32 @@ pub enum FunctionMode {
     Unknown(u8),
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Status of the function modes, answered after a function mode change or a status enquiry
+///
+/// <https://jbellue.github.io/stum1b/#2-6-11>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FunctionModeStatus {
+    pub rouleau: bool,
+    pub procedure: bool,
+    pub minuscule: bool,
+}
+
+impl From<u8> for FunctionModeStatus {
+    fn from(status: u8) -> Self {
+        FunctionModeStatus {
+            rouleau: status & 0b0010 != 0,
+            procedure: status & 0b0100 != 0,
+            minuscule: status & 0b1000 != 0,
+        }
+    }
+}
+
+/// Modules routed to a receiver, answered after a routing change or a routing enquiry
+///
+/// <https://jbellue.github.io/stum1b/#2-6-3>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RoutingStatus {
     pub prise: bool,
     pub modem: bool,
@@ -188,9 +245,96 @@ impl From<u8> for RoutingStatus {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Routed modules, such as "modem, keyboard", or "none"
+impl Display for RoutingStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let modules: Vec<_> = [
+            (self.prise, "prise"),
+            (self.modem, "modem"),
+            (self.keyboard, "keyboard"),
+            (self.screen, "screen"),
+        ]
+        .into_iter()
+        .filter_map(|(routed, name)| routed.then_some(name))
+        .collect();
+        if modules.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", modules.join(", "))
+        }
+    }
+}
+
+/// Active routings, from each emitter to each receiver
+///
+/// Indexed by emitter then receiver, in the order of their codes: screen, keyboard,
+/// modem and prise. The matrix only reflects the changes made through it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoutingMatrix(pub [[bool; 4]; 4]);
+
+impl RoutingMatrix {
+    /// Keyboard and modem routed to the screen
+    pub fn default_interactive() -> Self {
+        let mut matrix = Self::default();
+        matrix.set(RoutingTx::Keyboard, RoutingRx::Screen, true);
+        matrix.set(RoutingTx::Modem, RoutingRx::Screen, true);
+        matrix
+    }
+
+    /// Modem and prise routed to each other
+    pub fn default_passthrough() -> Self {
+        let mut matrix = Self::default();
+        matrix.set(RoutingTx::Modem, RoutingRx::Prise, true);
+        matrix.set(RoutingTx::Prise, RoutingRx::Modem, true);
+        matrix
+    }
+
+    fn index(tx: RoutingTx, rx: RoutingRx) -> Option<(usize, usize)> {
+        let tx = u8::from(tx).checked_sub(RoutingTx::Screen.into())?;
+        let rx = u8::from(rx).checked_sub(RoutingRx::Screen.into())?;
+        (tx < 4 && rx < 4).then_some((tx as usize, rx as usize))
+    }
+
+    /// Whether the emitter is routed to the receiver, `false` for unknown modules
+    pub fn is_routed(&self, tx: RoutingTx, rx: RoutingRx) -> bool {
+        Self::index(tx, rx).is_some_and(|(tx, rx)| self.0[tx][rx])
+    }
+
+    /// Record a routing change, ignored for unknown modules
+    pub fn set(&mut self, tx: RoutingTx, rx: RoutingRx, enable: bool) {
+        if let Some((tx, rx)) = Self::index(tx, rx) {
+            self.0[tx][rx] = enable;
+        }
+    }
+
+    /// Enable or disable a routing on the minitel, if it differs from the matrix
+    pub async fn set_route<M: AsyncMinitelReadWrite>(
+        &mut self,
+        minitel: &mut M,
+        tx: RoutingTx,
+        rx: RoutingRx,
+        enable: bool,
+    ) -> io::Result<()> {
+        if Self::index(tx, rx).is_none() {
+            return Err(ErrorKind::InvalidInput.into());
+        }
+        if self.is_routed(tx, rx) != enable {
+            minitel.set_routing(enable, rx, tx).await?;
+            self.set(tx, rx, enable);
+        }
+        Ok(())
+    }
+}
+
+/// Speed of the serial link, ordered from the slowest
+///
+/// The minitel starts at 1200 bauds, the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Baudrate {
     B300,
+    #[default]
     B1200,
     B4800,
     B9600,
@@ -206,6 +350,21 @@ impl Baudrate {
         }
     }
 
+    pub fn from_hertz(hertz: u32) -> Option<Self> {
+        Self::iter().find(|baudrate| baudrate.hertz() == hertz)
+    }
+
+    /// All the speeds, from the slowest
+    pub fn iter() -> impl Iterator<Item = Self> {
+        [
+            Baudrate::B300,
+            Baudrate::B1200,
+            Baudrate::B4800,
+            Baudrate::B9600,
+        ]
+        .into_iter()
+    }
+
     pub fn code(&self) -> u8 {
         // P 1 E2 E1 E0 R2 R1 R0
         // P: Parity
@@ -250,13 +409,14 @@ impl TryFrom<u8> for Baudrate {
 
 impl Display for Baudrate {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{} bauds", self.hertz())
+        write!(f, "{} baud", self.hertz())
     }
 }
 
 /// Content of the ROM retrived after PRO1 ENQROM
 /// Are omitted the SOH and EOT starting and ending bytes
 /// <https://jbellue.github.io/stum1b/#2-6-6>
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rom {
     pub manufacturer: u8,
     pub model: u8,
@@ -272,3 +432,208 @@ impl From<[u8; 3]> for Rom {
         }
     }
 }
+
+impl Rom {
+    /// Name of the model, "Inconnu" when the model code is not known
+    pub fn display_name(&self) -> &'static str {
+        MINITEL_MODELS
+            .iter()
+            .find(|(code, _, _)| *code == self.model)
+            .map_or("Inconnu", |(_, name, _)| name)
+    }
+
+    /// Name of the manufacturer, if known
+    pub fn manufacturer_name(&self) -> Option<&'static str> {
+        MINITEL_MANUFACTURERS
+            .iter()
+            .find(|(code, _)| *code == self.manufacturer)
+            .map(|(_, name)| *name)
+    }
+
+    /// Generation of the minitel, from its model code
+    pub fn generation(&self) -> MinitelGeneration {
+        MINITEL_MODELS
+            .iter()
+            .find(|(code, _, _)| *code == self.model)
+            .map_or(MinitelGeneration::Unknown, |(_, _, generation)| *generation)
+    }
+
+    /// Whether the minitel displays colors instead of gray levels
+    ///
+    /// Only the Minitel 1 couleur models, identified by their model code, are in color.
+    pub fn is_color(&self) -> bool {
+        matches!(self.model, b'e' | b's')
+    }
+}
+
+/// Manufacturer, model and version, such as "Telic-Alcatel Minitel 1B v4"
+impl Display for Rom {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} v{}",
+            self.manufacturer_name().unwrap_or("Inconnu"),
+            self.display_name(),
+            self.version as char
+        )
+    }
+}
+
+/// Family of a minitel model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MinitelGeneration {
+    Minitel1,
+    Minitel1B,
+    Minitel2,
+    Minitel5,
+    Minitel10,
+    Minitel12,
+    Emulator,
+    /// Known terminal, not a minitel (printer, Terminatel...)
+    Other,
+    Unknown,
+}
+
+/// Known model codes, second byte of the ROM identification, with their name and generation
+///
+/// The table is keyed by the model code alone, not by a (manufacturer, model) pair: the
+/// STUM assigns the model codes independently of the manufacturer, so the same code
+/// names the same terminal whoever built it. The manufacturers are listed apart in
+/// [`MINITEL_MANUFACTURERS`], and both are combined by the `Display` of [`Rom`].
+pub static MINITEL_MODELS: &[(u8, &str, MinitelGeneration)] = &[
+    (b'b', "Minitel 1", MinitelGeneration::Minitel1),
+    (b'c', "Minitel 1", MinitelGeneration::Minitel1),
+    (b'd', "Minitel 10", MinitelGeneration::Minitel10),
+    (b'e', "Minitel 1 couleur", MinitelGeneration::Minitel1),
+    (b'f', "Minitel 10", MinitelGeneration::Minitel10),
+    (b'g', "Émulateur", MinitelGeneration::Emulator),
+    (b'j', "Imprimante", MinitelGeneration::Other),
+    (b'r', "Minitel 1", MinitelGeneration::Minitel1),
+    (b's', "Minitel 1 couleur", MinitelGeneration::Minitel1),
+    (b't', "Terminatel 252", MinitelGeneration::Other),
+    (b'u', "Minitel 1B", MinitelGeneration::Minitel1B),
+    (b'v', "Minitel 2", MinitelGeneration::Minitel2),
+    (b'w', "Minitel 10B", MinitelGeneration::Minitel10),
+    (b'y', "Minitel 5", MinitelGeneration::Minitel5),
+    (b'z', "Minitel 12", MinitelGeneration::Minitel12),
+];
+
+/// Known manufacturer codes, first byte of the ROM identification
+pub static MINITEL_MANUFACTURERS: &[(u8, &str)] = &[
+    (b'A', "Matra"),
+    (b'B', "RTIC"),
+    (b'C', "Telic-Alcatel"),
+    (b'D', "Thomson"),
+    (b'E', "CCS"),
+    (b'F', "Fiet"),
+    (b'G', "Fime"),
+    (b'H', "Unitel"),
+    (b'I', "Option"),
+    (b'J', "Bull"),
+    (b'K', "Télématique"),
+    (b'L', "Desmet"),
+];
+
+/// Error in a protocol exchange with the minitel
+///
+/// Returned as the inner error of an [`std::io::Error`] of kind `InvalidData`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MinitelProtocolError {
+    /// A byte differs from the expected answer
+    UnexpectedByte {
+        /// Step of the exchange, such as "waiting for PRO2"
+        step: String,
+        expected: u8,
+        got: u8,
+    },
+}
+
+impl Display for MinitelProtocolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MinitelProtocolError::UnexpectedByte {
+                step,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Unexpected byte {got:#04X} instead of {expected:#04X} while {step}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MinitelProtocolError {}
+
+impl From<MinitelProtocolError> for std::io::Error {
+    fn from(error: MinitelProtocolError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn baudrate_eq_hash() {
+        let speeds: HashSet<Baudrate> = Baudrate::speeds().into_iter().collect();
+        assert_eq!(speeds.len(), 4);
+        for speed in Baudrate::speeds() {
+            assert!(speeds.contains(&speed));
+            assert_eq!(Baudrate::try_from(speed.code()), Ok(speed));
+        }
+        assert_ne!(Baudrate::B1200, Baudrate::B9600);
+        assert_eq!(Baudrate::B1200.to_string(), "1200 baud");
+    }
+
+    #[test]
+    fn baudrate_order() {
+        assert!(Baudrate::B300 < Baudrate::B4800);
+        assert_eq!(Baudrate::default(), Baudrate::B1200);
+        assert_eq!(Baudrate::from_hertz(9600), Some(Baudrate::B9600));
+        assert_eq!(Baudrate::from_hertz(2400), None);
+        let speeds: Vec<_> = Baudrate::iter().map(|b| b.hertz()).collect();
+        assert_eq!(speeds, vec![300, 1200, 4800, 9600]);
+    }
+
+    #[test]
+    fn routing_status() {
+        let status = RoutingStatus::from(0b0110);
+        assert_eq!(status.to_string(), "modem, keyboard");
+        assert_eq!(status, RoutingStatus::from(0b0110));
+        assert_eq!(RoutingStatus::from(0).to_string(), "none");
+    }
+
+    #[test]
+    fn routing_matrix() {
+        let matrix = RoutingMatrix::default_interactive();
+        assert!(matrix.is_routed(RoutingTx::Keyboard, RoutingRx::Screen));
+        assert!(matrix.is_routed(RoutingTx::Modem, RoutingRx::Screen));
+        assert!(!matrix.is_routed(RoutingTx::Keyboard, RoutingRx::Modem));
+        assert!(!matrix.is_routed(RoutingTx::Unknown(0x54), RoutingRx::Screen));
+
+        let matrix = RoutingMatrix::default_passthrough();
+        assert!(matrix.0[2][3]);
+        assert!(matrix.0[3][2]);
+    }
+
+    #[test]
+    fn rom_models() {
+        let rom = Rom::from([b'C', b'u', b'4']);
+        assert_eq!(rom.display_name(), "Minitel 1B");
+        assert_eq!(rom.manufacturer_name(), Some("Telic-Alcatel"));
+        assert_eq!(rom.generation(), MinitelGeneration::Minitel1B);
+        assert_eq!(rom.to_string(), "Telic-Alcatel Minitel 1B v4");
+        assert!(!rom.is_color());
+        assert!(Rom::from([b'A', b's', b'1']).is_color());
+
+        let rom = Rom::from([b'?', b'?', b'1']);
+        assert_eq!(rom.display_name(), "Inconnu");
+        assert_eq!(rom.manufacturer_name(), None);
+        assert_eq!(rom.generation(), MinitelGeneration::Unknown);
+    }
+}
@@ -1,3 +1,8 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+};
+
 use num_enum::{FromPrimitive, IntoPrimitive};
 use smallvec::SmallVec;
 use unicode_normalization::UnicodeNormalization;
@@ -8,6 +13,7 @@ use super::protocol::ProtocolMessage;
 
 /// Virtual keystroke sequence
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UserInput {
     /// A single character, G0 or G2
     Char(char),
@@ -21,15 +27,70 @@ pub enum UserInput {
     Protocol(ProtocolMessage),
 }
 
+impl UserInput {
+    /// Character input, if the minitel can encode it
+    pub fn from_char(c: char) -> Option<Self> {
+        SIChar::try_from(c).ok().map(|_| UserInput::Char(c))
+    }
+
+    /// Function key from its French or English name, case insensitive
+    pub fn from_function_key_name(name: &str) -> Option<Self> {
+        let key = match name.to_lowercase().as_str() {
+            "envoi" | "send" => FunctionKey::Envoi,
+            "retour" | "back" | "previous" => FunctionKey::Retour,
+            "répétition" | "repetition" | "repeat" => FunctionKey::Repetition,
+            "guide" | "help" => FunctionKey::Guide,
+            "annulation" | "cancel" => FunctionKey::Annulation,
+            "sommaire" | "index" | "menu" => FunctionKey::Sommaire,
+            "correction" | "delete" => FunctionKey::Correction,
+            "suite" | "next" => FunctionKey::Suite,
+            "connexion/fin" | "connexionfin" | "connexion" | "fin" | "connection" | "end" => {
+                FunctionKey::ConnexionFin
+            }
+            _ => return None,
+        };
+        Some(UserInput::FunctionKey(key))
+    }
+}
+
+/// Readable input, for logs: `'H'`, `[Envoi]`, `ESC BgBlue`, `CR`...
+impl Display for UserInput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            UserInput::Char(c) => write!(f, "{c:?}"),
+            UserInput::C0(c0) => write!(f, "{c0}"),
+            UserInput::C1(c1) => write!(f, "ESC {c1}"),
+            UserInput::FunctionKey(key) => write!(f, "[{key}]"),
+            UserInput::Protocol(message) => write!(f, "{message:?}"),
+        }
+    }
+}
+
+/// Number of columns used by the text on the minitel
+///
+/// Characters with diacritics take a single column, characters that can not be encoded are skipped.
+pub fn display_width(text: &str) -> usize {
+    text.chars()
+        .filter(|c| SIChar::try_from(*c).is_ok())
+        .count()
+}
+
 pub struct StringMessage(pub String);
 
+impl StringMessage {
+    /// Append the encoded string to an existing buffer
+    pub fn message_into(&self, buf: &mut Vec<u8>) {
+        for c in self.0.chars().flat_map(SIChar::try_from) {
+            c.encode_to(buf);
+        }
+    }
+}
+
 impl MinitelMessage for StringMessage {
     fn message(self) -> Vec<u8> {
-        self.0
-            .chars()
-            .flat_map(SIChar::try_from)
-            .flat_map(|c| c.message())
-            .collect()
+        let mut buf = Vec::with_capacity(self.0.len());
+        self.message_into(&mut buf);
+        buf
     }
 }
 
@@ -44,9 +105,14 @@ impl MinitelMessage for SetPosition {
 /// <https://jbellue.github.io/stum1b/#2-2-1-2-4-2>
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum C0 {
     NUL = 0x00,
     SOH = 0x01,
+    /// Start of text, opening a frame after DLE
+    STX = 0x02,
+    /// End of text, closing a frame after DLE
+    ETX = 0x03,
     EOL = 0x04,
     ENQ = 0x05,
     BEL = 0x07,
@@ -98,10 +164,21 @@ impl MinitelMessage for C0 {
     }
 }
 
+/// Name of the control character, for logs
+impl Display for C0 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            C0::Other(code) => write!(f, "C0::Other(0x{:02X})", code),
+            c0 => write!(f, "{:?}", c0),
+        }
+    }
+}
+
 /// ESC control character
 /// <https://jbellue.github.io/stum1b/#2-2-1-2-4-2>
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, FromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, IntoPrimitive, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum C1 {
     /// Protocol message with one parameter
     /// Not listed as C1, but used like one after ESC
@@ -171,16 +248,111 @@ pub enum C1 {
     Other(u8),
 }
 
+impl C1 {
+    /// Foreground color attribute, from `CharBlack` to `CharWhite`
+    pub fn is_char_color(&self) -> bool {
+        (0x40..=0x47).contains(&u8::from(*self))
+    }
+
+    /// Background color attribute, from `BgBlack` to `BgWhite`
+    pub fn is_bg_color(&self) -> bool {
+        (0x50..=0x57).contains(&u8::from(*self))
+    }
+
+    /// Background attribute of the same color, `None` if not a color attribute
+    pub fn corresponding_bg(&self) -> Option<C1> {
+        match u8::from(*self) {
+            code @ 0x40..=0x47 => Some(C1::from(code + 0x10)),
+            0x50..=0x57 => Some(*self),
+            _ => None,
+        }
+    }
+
+    /// Foreground attribute of the same color, `None` if not a color attribute
+    pub fn corresponding_char(&self) -> Option<C1> {
+        match u8::from(*self) {
+            0x40..=0x47 => Some(*self),
+            code @ 0x50..=0x57 => Some(C1::from(code - 0x10)),
+            _ => None,
+        }
+    }
+}
+
+/// Name of the control character, for logs
+impl Display for C1 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            C1::Other(code) => write!(f, "C1::Other(0x{:02X})", code),
+            c1 => write!(f, "{:?}", c1),
+        }
+    }
+}
+
 impl MinitelMessage for C1 {
     fn message(self) -> Vec<u8> {
         vec![C0::ESC.into(), self.into()]
     }
 }
 
+/// Sequences introduced by ESC CSI, for cursor moves and erasures
+///
+/// The STUM does not define any sequence to save and restore the cursor position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsiSequence {
+    /// Move the cursor up by n rows
+    CursorUp(u8),
+    /// Move the cursor down by n rows
+    CursorDown(u8),
+    /// Move the cursor right by n columns
+    CursorRight(u8),
+    /// Move the cursor left by n columns
+    CursorLeft(u8),
+    /// Erase from the cursor to the end of the screen
+    EraseToEndOfScreen,
+    /// Erase from the start of the screen to the cursor
+    EraseFromStartOfScreen,
+    /// Erase the whole screen, without moving the cursor
+    EraseScreen,
+    /// Erase from the cursor to the end of the line
+    EraseToEndOfLine,
+    /// Erase from the start of the line to the cursor
+    EraseFromStartOfLine,
+    /// Erase the whole line, without moving the cursor
+    EraseLine,
+    /// Delete n rows from the cursor row, the rows below move up
+    DeleteLines(u8),
+}
+
+impl MinitelMessage for CsiSequence {
+    fn message(self) -> Vec<u8> {
+        let (param, end) = match self {
+            CsiSequence::CursorUp(n) => (Some(n), b'A'),
+            CsiSequence::CursorDown(n) => (Some(n), b'B'),
+            CsiSequence::CursorRight(n) => (Some(n), b'C'),
+            CsiSequence::CursorLeft(n) => (Some(n), b'D'),
+            CsiSequence::EraseToEndOfScreen => (None, b'J'),
+            CsiSequence::EraseFromStartOfScreen => (Some(1), b'J'),
+            CsiSequence::EraseScreen => (Some(2), b'J'),
+            CsiSequence::EraseToEndOfLine => (None, b'K'),
+            CsiSequence::EraseFromStartOfLine => (Some(1), b'K'),
+            CsiSequence::EraseLine => (Some(2), b'K'),
+            CsiSequence::DeleteLines(n) => (Some(n), b'M'),
+        };
+        let mut message = C1::Csi.message();
+        if let Some(param) = param {
+            // Decimal parameter, written in ascii
+            message.extend(param.to_string().bytes());
+        }
+        message.push(end);
+        message
+    }
+}
+
 /// G0 characters (nearly ascii)
 ///
 /// <https://jbellue.github.io/stum1b/#2-2-1-2-8>
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct G0(pub u8);
 
 impl From<G0> for u8 {
@@ -211,6 +383,33 @@ impl From<G0> for char {
     }
 }
 
+impl G0 {
+    /// Visible character, excluding the space
+    pub fn is_printable(&self) -> bool {
+        (0x21..=0x7E).contains(&self.0)
+    }
+
+    /// All the characters, from the space (0x20) to 0x7E
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0x20..=0x7E).map(G0)
+    }
+
+    /// All the characters, with their unicode equivalent
+    pub fn all_chars() -> impl Iterator<Item = (Self, char)> {
+        Self::all().map(|g0| (g0, g0.into()))
+    }
+}
+
+/// Unicode equivalent of the character, or its code if out of the G0 set
+impl Display for G0 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match G0_TO_CHAR.get((self.0 as usize).wrapping_sub(0x20)) {
+            Some(c) => write!(f, "{c}"),
+            None => write!(f, "G0(0x{:02X})", self.0),
+        }
+    }
+}
+
 impl TryFrom<u8> for G0 {
     type Error = ();
 
@@ -231,6 +430,8 @@ impl TryFrom<char> for G0 {
             '\u{0020}'..='\u{005D}' | '\u{005F}' | '\u{0061}'..='\u{007A}' | '\u{007C}' => {
                 Ok(G0(value as u8))
             }
+            // Non ascii characters
+            '↑' => Ok(G0(0x5E)),
             // Drawing characters
             '▁' => Ok(G0(0x5F)),
             '─' => Ok(G0(0x60)),
@@ -244,8 +445,13 @@ impl TryFrom<char> for G0 {
 
 /// Semi-graphic sextant characters
 ///
+/// The mosaic set of the minitel only has 2×3 pixels per character, there is no taller
+/// encoding in the videotex modes. Finer drawings use the redefinable characters of
+/// the Minitel 2 (DRCS) instead.
+///
 /// <https://jbellue.github.io/stum1b/#2-2-1-2-8>
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct G1(pub u8);
 
 impl From<G1> for u8 {
@@ -260,6 +466,12 @@ impl MinitelMessage for G1 {
     }
 }
 
+impl Display for G1 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_unicode())
+    }
+}
+
 impl G1 {
     // Sextant from the unicode Symbols for Legacy Computing (U+1FB0x...)
     // https://en.wikipedia.org/wiki/Symbols_for_Legacy_Computing
@@ -273,10 +485,52 @@ impl G1 {
         '⠭', '⠦', '⠧', '⠮', '⠯', '⠴', '⠵', '⠼', '⠽', '⠶', '⠷', '⠾'
     ];
 
+    // Quadrants, named after ratatui::symbols
+    pub const QUADRANT_BLANK: G1 = G1::from_quadrants(false, false, false, false);
+    pub const QUADRANT_UPPER_LEFT: G1 = G1::from_quadrants(true, false, false, false);
+    pub const QUADRANT_UPPER_RIGHT: G1 = G1::from_quadrants(false, true, false, false);
+    pub const QUADRANT_LOWER_LEFT: G1 = G1::from_quadrants(false, false, true, false);
+    pub const QUADRANT_LOWER_RIGHT: G1 = G1::from_quadrants(false, false, false, true);
+    pub const QUADRANT_UPPER_HALF: G1 = G1::from_quadrants(true, true, false, false);
+    pub const QUADRANT_LOWER_HALF: G1 = G1::from_quadrants(false, false, true, true);
+    pub const QUADRANT_LEFT_HALF: G1 = G1::from_quadrants(true, false, true, false);
+    pub const QUADRANT_RIGHT_HALF: G1 = G1::from_quadrants(false, true, false, true);
+    pub const QUADRANT_UPPER_LEFT_LOWER_RIGHT: G1 = G1::from_quadrants(true, false, false, true);
+    pub const QUADRANT_UPPER_RIGHT_LOWER_LEFT: G1 = G1::from_quadrants(false, true, true, false);
+    pub const QUADRANT_UPPER_LEFT_UPPER_RIGHT_LOWER_LEFT: G1 =
+        G1::from_quadrants(true, true, true, false);
+    pub const QUADRANT_UPPER_LEFT_UPPER_RIGHT_LOWER_RIGHT: G1 =
+        G1::from_quadrants(true, true, false, true);
+    pub const QUADRANT_UPPER_LEFT_LOWER_LEFT_LOWER_RIGHT: G1 =
+        G1::from_quadrants(true, false, true, true);
+    pub const QUADRANT_UPPER_RIGHT_LOWER_LEFT_LOWER_RIGHT: G1 =
+        G1::from_quadrants(false, true, true, true);
+
     pub fn new(val: u8) -> Self {
         G1(val)
     }
 
+    /// The 64 mosaic patterns, from the empty one to the full block
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0x20..=0x3F).chain(0x60..=0x7F).map(G1)
+    }
+
+    /// Build a G1 character from 2×2 quadrants
+    ///
+    /// The top quadrants cover the two first rows of the character, the bottom ones the last row.
+    pub const fn from_quadrants(
+        top_left: bool,
+        top_right: bool,
+        bottom_left: bool,
+        bottom_right: bool,
+    ) -> Self {
+        G1(0x20
+            | (top_left as u8 * 0b0000_0101)
+            | (top_right as u8 * 0b0000_1010)
+            | (bottom_left as u8 * 0b0001_0000)
+            | (bottom_right as u8 * 0b0100_0000))
+    }
+
     /// Convert from the 3 rows of 2 bits into a G1 character
     /// [[1, 2],
     /// [3, 4],
@@ -292,11 +546,74 @@ impl G1 {
         G1(val)
     }
 
+    /// Split the G1 character into its 3 rows of 2 pixels, inverse of [`G1::from_bits`]
+    pub const fn bits(&self) -> [[bool; 2]; 3] {
+        [
+            [self.0 & 0b0000_0001 != 0, self.0 & 0b0000_0010 != 0],
+            [self.0 & 0b0000_0100 != 0, self.0 & 0b0000_1000 != 0],
+            [self.0 & 0b0001_0000 != 0, self.0 & 0b0100_0000 != 0],
+        ]
+    }
+
+    /// Whether the pixel at the given row (0 to 2) and column (0 or 1) is set
+    ///
+    /// Pixels outside of the character are never set.
+    pub fn is_set(&self, row: usize, col: usize) -> bool {
+        row < 3 && col < 2 && self.bits()[row][col]
+    }
+
+    /// All the pixels are set
+    pub fn is_full_block(&self) -> bool {
+        self.0 == 0x7F
+    }
+
+    /// No pixel is set
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0x20
+    }
+
+    /// Only the two top rows are set, as [`G1::QUADRANT_UPPER_HALF`]
+    pub fn is_top_half(&self) -> bool {
+        *self == Self::QUADRANT_UPPER_HALF
+    }
+
+    /// Only the bottom row is set, as [`G1::QUADRANT_LOWER_HALF`]
+    pub fn is_bottom_half(&self) -> bool {
+        *self == Self::QUADRANT_LOWER_HALF
+    }
+
+    /// Pixels set in either character
+    pub fn blend(a: G1, b: G1) -> G1 {
+        G1(a.0 | b.0)
+    }
+
+    /// Unicode character drawing the same pixels, converted back by [`G1::approximate_char`]
+    ///
+    /// The patterns are taken from the sextants of the Symbols for Legacy Computing block.
+    /// The four patterns missing there use the existing block elements: ' ', '▌', '▐' and '█'.
+    pub fn to_unicode(&self) -> char {
+        let pixels = (self.0 & 0b0001_1111) | ((self.0 & 0b0100_0000) >> 1);
+        match pixels {
+            0b00_0000 => ' ',
+            0b01_0101 => '▌',
+            0b10_1010 => '▐',
+            0b11_1111 => '█',
+            // sextants are ordered by pixels value, without the skipped patterns
+            _ => {
+                let skipped = (pixels > 0b01_0101) as u32 + (pixels > 0b10_1010) as u32;
+                char::from_u32(0x1FB00 + pixels as u32 - 1 - skipped).unwrap_or(' ')
+            }
+        }
+    }
+
     /// Render the approximate semi graphic character matching the unicode value
+    ///
+    /// In the Symbols for Legacy Computing block, the sextants and the block fractions
+    /// are approximated. The smooth mosaics, the one eighth lines and the shades are not.
     pub fn approximate_char(c: char) -> Option<Self> {
         let c = match c {
             // sextants: use braille as intermediate
-            '\u{1FB00}'..='\u{1FB3C}' => Self::SEXTANT_TO_BRAILLE[c as usize - 0x1FB00],
+            '\u{1FB00}'..='\u{1FB3B}' => Self::SEXTANT_TO_BRAILLE[c as usize - 0x1FB00],
             _ => c,
         };
         match c {
@@ -343,6 +660,18 @@ impl G1 {
             '▃' => Some(G1(0x70)),
             '▂' => Some(G1(0x70)),
             '▁' => Some(G1(0x20)),
+            // upper block fractions
+            '\u{1FB82}' => Some(G1(0x20)),
+            '\u{1FB83}' => Some(G1(0x23)),
+            '\u{1FB84}' => Some(G1(0x2F)),
+            '\u{1FB85}' => Some(G1(0x2F)),
+            '\u{1FB86}' => Some(G1(0x7F)),
+            // right block fractions
+            '\u{1FB87}' => Some(G1(0x20)),
+            '\u{1FB88}' => Some(G1(0x6A)),
+            '\u{1FB89}' => Some(G1(0x6A)),
+            '\u{1FB8A}' => Some(G1(0x7F)),
+            '\u{1FB8B}' => Some(G1(0x7F)),
             _ => None,
         }
     }
@@ -350,7 +679,8 @@ impl G1 {
 
 /// <https://jbellue.github.io/stum1b/#2-2-1-2-8>
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, FromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, IntoPrimitive, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum G2 {
     Pound = 0x23,
     Dollar = 0x24,
@@ -373,6 +703,7 @@ pub enum G2 {
     Cedille = 0x4B,
     OeMaj = 0x6A,
     OeMin = 0x7A,
+    /// Eszett (ß), also used for the similar looking beta (β)
     Beta = 0x7B,
     #[num_enum(catch_all)]
     Unknown(u8),
@@ -385,6 +716,35 @@ impl MinitelMessage for G2 {
 }
 
 impl G2 {
+    /// All the characters of the set, including the diacritics
+    pub fn all() -> impl Iterator<Item = Self> {
+        [
+            G2::Pound,
+            G2::Dollar,
+            G2::Hash,
+            G2::Section,
+            G2::LeftArrow,
+            G2::UpArrow,
+            G2::RightArrow,
+            G2::DownArrow,
+            G2::Degree,
+            G2::PlusMinus,
+            G2::Division,
+            G2::OneQuarter,
+            G2::OneHalf,
+            G2::ThreeQuarters,
+            G2::Grave,
+            G2::Acute,
+            G2::Circumflex,
+            G2::Diaeresis,
+            G2::Cedille,
+            G2::OeMaj,
+            G2::OeMin,
+            G2::Beta,
+        ]
+        .into_iter()
+    }
+
     pub fn char(self) -> char {
         match self {
             G2::Pound => '£',
@@ -408,7 +768,7 @@ impl G2 {
             G2::Cedille => '¸',
             G2::OeMaj => 'Œ',
             G2::OeMin => 'œ',
-            G2::Beta => 'β',
+            G2::Beta => 'ß',
             G2::Unknown(_) => ' ',
         }
     }
@@ -424,6 +784,21 @@ impl G2 {
         }
     }
 
+    /// Convert a standalone diacritic mark into its G2 character
+    ///
+    /// These are deliberately left out of `TryFrom<char>`: when sent alone, the minitel
+    /// waits for the next character to compose it with the diacritic.
+    pub fn try_from_standalone(c: char) -> Option<Self> {
+        match c {
+            '`' => Some(G2::Grave),
+            '´' => Some(G2::Acute),
+            '^' => Some(G2::Circumflex),
+            '¨' => Some(G2::Diaeresis),
+            '¸' => Some(G2::Cedille),
+            _ => None,
+        }
+    }
+
     pub fn try_from_diactric(c: char) -> Option<Self> {
         match c {
             '\u{0300}' => Some(G2::Grave),
@@ -455,14 +830,10 @@ impl TryFrom<char> for G2 {
             '¼' => Ok(G2::OneQuarter),
             '½' => Ok(G2::OneHalf),
             '¾' => Ok(G2::ThreeQuarters),
-            //'`' => Ok(G2::Grave),
-            //'´' => Ok(G2::Acute),
-            //'^' => Ok(G2::Circumflex),
-            //'¨' => Ok(G2::Diaeresis),
-            //'¸' => Ok(G2::Cedille),
+            // Standalone diacritics are handled by `G2::try_from_standalone`
             'Œ' => Ok(G2::OeMaj),
             'œ' => Ok(G2::OeMin),
-            'β' => Ok(G2::Beta),
+            'ß' | 'β' => Ok(G2::Beta),
             _ => Err(()),
         }
     }
@@ -470,6 +841,7 @@ impl TryFrom<char> for G2 {
 
 /// Normal characters ("code")
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SIChar {
     /// Basic character, nearly ascii
     G0(G0),
@@ -479,16 +851,38 @@ pub enum SIChar {
     G2(G2),
 }
 
-impl MinitelMessage for SIChar {
-    fn message(self) -> Vec<u8> {
+impl SIChar {
+    /// Convert the character, replacing it with '?' when the minitel cannot display it
+    pub fn lossy(c: char) -> Self {
+        Self::try_from(c).unwrap_or(SIChar::G0(G0(b'?')))
+    }
+
+    /// Convert the character, replacing it with `replacement` when the minitel cannot display it
+    ///
+    /// Returns `None` when the replacement is not a G0 character.
+    pub fn lossy_with(c: char, replacement: char) -> Option<Self> {
+        let replacement = G0::try_from(replacement).ok()?;
+        Some(Self::try_from(c).unwrap_or(SIChar::G0(replacement)))
+    }
+
+    /// Append the encoded character to a buffer, without intermediate allocation
+    pub fn encode_to(self, buf: &mut impl Extend<u8>) {
         match self {
-            SIChar::G0(g0) => g0.message(),
-            SIChar::G0Diacritic(g0, g2) => [g2.message(), g0.message()].concat(),
-            SIChar::G2(g2) => g2.message(),
+            SIChar::G0(g0) => buf.extend([g0.into()]),
+            SIChar::G0Diacritic(g0, g2) => buf.extend([C0::SS2.into(), g2.into(), g0.into()]),
+            SIChar::G2(g2) => buf.extend([C0::SS2.into(), g2.into()]),
         }
     }
 }
 
+impl MinitelMessage for SIChar {
+    fn message(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(3);
+        self.encode_to(&mut buf);
+        buf
+    }
+}
+
 impl TryFrom<char> for SIChar {
     type Error = ();
 
@@ -520,7 +914,8 @@ impl TryFrom<char> for SIChar {
 ///
 /// <https://jbellue.github.io/stum1b/#2-3-6>
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, FromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, IntoPrimitive, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FunctionKey {
     Envoi = 0x41,
     Retour = 0x42,
@@ -541,9 +936,63 @@ impl MinitelMessage for FunctionKey {
     }
 }
 
+/// Ordered by key code
+impl Ord for FunctionKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        u8::from(*self).cmp(&u8::from(*other))
+    }
+}
+
+impl PartialOrd for FunctionKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FunctionKey {
+    /// Label printed on the key, "Inconnue" for an unknown key
+    pub fn label_fr(&self) -> &'static str {
+        match self {
+            FunctionKey::Envoi => "Envoi",
+            FunctionKey::Retour => "Retour",
+            FunctionKey::Repetition => "Répétition",
+            FunctionKey::Guide => "Guide",
+            FunctionKey::Annulation => "Annulation",
+            FunctionKey::Sommaire => "Sommaire",
+            FunctionKey::Correction => "Correction",
+            FunctionKey::Suite => "Suite",
+            FunctionKey::ConnexionFin => "Connexion/Fin",
+            FunctionKey::Unknown(_) => "Inconnue",
+        }
+    }
+}
+
+/// Label printed on the key, or its code when unknown
+impl Display for FunctionKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FunctionKey::Unknown(code) => write!(f, "FunctionKey(0x{:02X})", code),
+            key => write!(f, "{}", key.label_fr()),
+        }
+    }
+}
+
+/// Function key of the input, the input itself otherwise
+impl TryFrom<UserInput> for FunctionKey {
+    type Error = UserInput;
+
+    fn try_from(input: UserInput) -> Result<Self, Self::Error> {
+        match input {
+            UserInput::FunctionKey(key) => Ok(key),
+            input => Err(input),
+        }
+    }
+}
+
 /// Convenience for black&white minitels
 ///
 /// <https://jbellue.github.io/stum1b/#1-3-2-4-3>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GrayScale {
     Black,
     Gray40,
@@ -556,6 +1005,44 @@ pub enum GrayScale {
 }
 
 impl GrayScale {
+    const ALL: [GrayScale; 8] = [
+        GrayScale::Black,
+        GrayScale::Gray40,
+        GrayScale::Gray50,
+        GrayScale::Gray60,
+        GrayScale::Gray70,
+        GrayScale::Gray80,
+        GrayScale::Gray90,
+        GrayScale::White,
+    ];
+
+    /// Luminance in percent
+    pub fn percent(&self) -> u8 {
+        match self {
+            GrayScale::Black => 0,
+            GrayScale::Gray40 => 40,
+            GrayScale::Gray50 => 50,
+            GrayScale::Gray60 => 60,
+            GrayScale::Gray70 => 70,
+            GrayScale::Gray80 => 80,
+            GrayScale::Gray90 => 90,
+            GrayScale::White => 100,
+        }
+    }
+
+    /// Closest gray level of a luminance from 0.0 to 1.0, clamped
+    pub fn from_luminance(l: f32) -> Self {
+        let percent = l.clamp(0.0, 1.0) * 100.0;
+        Self::ALL
+            .into_iter()
+            .min_by(|a, b| {
+                (a.percent() as f32 - percent)
+                    .abs()
+                    .total_cmp(&(b.percent() as f32 - percent).abs())
+            })
+            .unwrap_or(GrayScale::Black)
+    }
+
     pub fn char(&self) -> C1 {
         match self {
             GrayScale::Black => C1::CharBlack,
@@ -583,7 +1070,118 @@ impl GrayScale {
     }
 }
 
-/// Repeat the character
+/// Gray level from 0 (black) to 7 (white), saturating
+impl From<u8> for GrayScale {
+    fn from(index: u8) -> Self {
+        GrayScale::ALL[index.min(7) as usize]
+    }
+}
+
+/// Luminance, such as "40%"
+impl Display for GrayScale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.percent())
+    }
+}
+
+/// Foreground and background colors
+///
+/// The foreground is expected to be one of the `C1::Char*` attributes,
+/// and the background one of the `C1::Bg*` attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColorPair {
+    pub fg: C1,
+    pub bg: C1,
+}
+
+impl ColorPair {
+    pub fn new(fg: C1, bg: C1) -> Self {
+        Self { fg, bg }
+    }
+
+    /// Attributes to send to go from one color pair to the other
+    pub fn diff(from: &Self, to: &Self) -> Vec<C1> {
+        let mut attributes = Vec::new();
+        if from.fg != to.fg {
+            attributes.push(to.fg);
+        }
+        if from.bg != to.bg {
+            attributes.push(to.bg);
+        }
+        attributes
+    }
+}
+
+impl Default for ColorPair {
+    fn default() -> Self {
+        Self::new(C1::CharWhite, C1::BgBlack)
+    }
+}
+
+impl From<ColorPair> for Vec<C1> {
+    fn from(colors: ColorPair) -> Self {
+        vec![colors.fg, colors.bg]
+    }
+}
+
+/// Semigraphic drawing with colors, as G1 characters of 2×3 pixels
+///
+/// Each character has its own foreground and background colors, shared by its 6 pixels.
+/// Pixels outside of the canvas are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColoredCanvas {
+    cells: Vec<Vec<(G1, C1, C1)>>,
+}
+
+impl ColoredCanvas {
+    /// Empty canvas of `cols`×`rows` characters, with the given colors
+    pub fn new(cols: u8, rows: u8, fg: C1, bg: C1) -> Self {
+        Self {
+            cells: vec![vec![(G1::QUADRANT_BLANK, fg, bg); cols as usize]; rows as usize],
+        }
+    }
+
+    /// Characters of the canvas, with their foreground and background colors
+    pub fn cells(&self) -> &[Vec<(G1, C1, C1)>] {
+        &self.cells
+    }
+
+    /// Set or clear the pixel at (x, y), from the top left pixel
+    pub fn set_pixel(&mut self, x: u8, y: u8, on: bool) {
+        let mask = Self::pixel_mask(x, y);
+        if let Some((g1, _, _)) = self.cell_mut(x, y) {
+            if on {
+                g1.0 |= mask;
+            } else {
+                g1.0 &= !mask;
+            }
+        }
+    }
+
+    /// Assign the colors of the character containing the pixel at (x, y)
+    pub fn set_pixel_color(&mut self, x: u8, y: u8, fg: C1, bg: C1) {
+        if let Some(cell) = self.cell_mut(x, y) {
+            (cell.1, cell.2) = (fg, bg);
+        }
+    }
+
+    fn cell_mut(&mut self, x: u8, y: u8) -> Option<&mut (G1, C1, C1)> {
+        self.cells.get_mut(y as usize / 3)?.get_mut(x as usize / 2)
+    }
+
+    /// Bit of the pixel in its G1 character, see [`G1::from_bits`]
+    fn pixel_mask(x: u8, y: u8) -> u8 {
+        match (y % 3) * 2 + x % 2 {
+            5 => 1 << 6,
+            bit => 1 << bit,
+        }
+    }
+}
+
+/// Repeat the last displayed character, from 1 to 63 times
+///
+/// <https://jbellue.github.io/stum1b/#2-2-1-2-4-2>
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Repeat(pub u8);
 
@@ -593,9 +1191,206 @@ impl MinitelMessage for Repeat {
     }
 }
 
+/// Invalid sequence found by [`validate_page`], with its offset in the page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageError {
+    /// Position outside of the screen
+    InvalidPosition(usize),
+    /// Byte outside of the character sets, or unknown G2 character
+    InvalidCharacter(usize),
+    /// Unknown sequence after ESC
+    InvalidAttribute(usize),
+    /// Repetition count out of 1 to 63
+    InvalidRepeat(usize),
+    /// Sequence cut by the end of the page
+    Truncated(usize),
+}
+
+impl PageError {
+    pub const fn message(&self) -> &'static str {
+        match self {
+            PageError::InvalidPosition(_) => "position outside of the screen",
+            PageError::InvalidCharacter(_) => "invalid character",
+            PageError::InvalidAttribute(_) => "invalid attribute after ESC",
+            PageError::InvalidRepeat(_) => "repetition count out of 1 to 63",
+            PageError::Truncated(_) => "sequence cut by the end of the page",
+        }
+    }
+}
+
+/// Check the sequences of a raw videotex page, usable in constants
+///
+/// Positions are expected in the form sent by [`SetPosition`]. See [`crate::minitel_page`]
+/// to check a page at compile time.
+pub const fn validate_page(page: &[u8]) -> Result<(), PageError> {
+    let mut i = 0;
+    while i < page.len() {
+        let len = match page[i] {
+            // US row column
+            0x1F => {
+                if i + 2 >= page.len() {
+                    return Err(PageError::Truncated(i));
+                }
+                if !matches!(page[i + 1], 0x40..=0x58) || !matches!(page[i + 2], 0x41..=0x68) {
+                    return Err(PageError::InvalidPosition(i));
+                }
+                3
+            }
+            // SS2 G2, or SS2 diacritic letter
+            0x19 => {
+                if i + 1 >= page.len() {
+                    return Err(PageError::Truncated(i));
+                }
+                match page[i + 1] {
+                    0x41 | 0x42 | 0x43 | 0x48 | 0x4B => {
+                        if i + 2 >= page.len() {
+                            return Err(PageError::Truncated(i));
+                        }
+                        if !page[i + 2].is_ascii_alphabetic() {
+                            return Err(PageError::InvalidCharacter(i));
+                        }
+                        3
+                    }
+                    0x23
+                    | 0x24
+                    | 0x26
+                    | 0x27
+                    | 0x2C..=0x31
+                    | 0x38
+                    | 0x3C..=0x3E
+                    | 0x6A
+                    | 0x7A
+                    | 0x7B => 2,
+                    _ => return Err(PageError::InvalidCharacter(i)),
+                }
+            }
+            // REP count
+            0x12 => {
+                if i + 1 >= page.len() {
+                    return Err(PageError::Truncated(i));
+                }
+                if !matches!(page[i + 1], 0x41..=0x7F) {
+                    return Err(PageError::InvalidRepeat(i));
+                }
+                2
+            }
+            0x1B => {
+                if i + 1 >= page.len() {
+                    return Err(PageError::Truncated(i));
+                }
+                let len = match page[i + 1] {
+                    // PRO1, PRO2, PRO3
+                    0x39 => 3,
+                    0x3A => 4,
+                    0x3B => 5,
+                    // Screen masking
+                    0x23 => 4,
+                    // CSI parameters final
+                    0x5B => {
+                        let mut j = i + 2;
+                        while j < page.len() && matches!(page[j], 0x30..=0x3F) {
+                            j += 1;
+                        }
+                        if j < page.len() && !matches!(page[j], 0x40..=0x7E) {
+                            return Err(PageError::InvalidAttribute(i));
+                        }
+                        j + 1 - i
+                    }
+                    0x40..=0x49 | 0x4C..=0x5A | 0x5C | 0x5D | 0x5F | 0x61 => 2,
+                    _ => return Err(PageError::InvalidAttribute(i)),
+                };
+                if i + len > page.len() {
+                    return Err(PageError::Truncated(i));
+                }
+                len
+            }
+            0x00..=0x7F => 1,
+            _ => return Err(PageError::InvalidCharacter(i)),
+        };
+        i += len;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn user_input_serde_round_trip() {
+        let inputs = [
+            UserInput::Char('é'),
+            UserInput::FunctionKey(FunctionKey::Envoi),
+            UserInput::C0(C0::Other(0x1C)),
+            UserInput::C1(C1::BgBlue),
+        ];
+        for input in inputs {
+            let json = serde_json::to_string(&input).unwrap();
+            assert_eq!(serde_json::from_str::<UserInput>(&json).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn user_input_constructors() {
+        let inputs: Vec<_> = "Été ☃".chars().flat_map(UserInput::from_char).collect();
+        assert_eq!(
+            inputs,
+            vec![
+                UserInput::Char('É'),
+                UserInput::Char('t'),
+                UserInput::Char('é'),
+                UserInput::Char(' '),
+            ]
+        );
+        assert_eq!(
+            UserInput::from_function_key_name("Envoi"),
+            Some(UserInput::FunctionKey(FunctionKey::Envoi))
+        );
+        assert_eq!(
+            UserInput::from_function_key_name("next"),
+            Some(UserInput::FunctionKey(FunctionKey::Suite))
+        );
+        assert_eq!(
+            UserInput::from_function_key_name("RÉPÉTITION"),
+            Some(UserInput::FunctionKey(FunctionKey::Repetition))
+        );
+        assert_eq!(UserInput::from_function_key_name("escape"), None);
+    }
+
+    #[test]
+    fn text_display_width() {
+        assert_eq!(display_width("Minitel"), 7);
+        assert_eq!(display_width("Éléphant"), 8);
+        assert_eq!(display_width("☃"), 0);
+    }
+
+    #[test]
+    fn control_display() {
+        assert_eq!(C0::ESC.to_string(), "ESC");
+        assert_eq!(C0::LF.to_string(), "LF");
+        assert_eq!(C0::from(0x1C).to_string(), "C0::Other(0x1C)");
+        assert_eq!(C1::BgRed.to_string(), "BgRed");
+        assert_eq!(C1::from(0x7E).to_string(), "C1::Other(0x7E)");
+    }
+
+    #[test]
+    fn color_pair_diff() {
+        let white_on_black = ColorPair::default();
+        let red_on_black = ColorPair::new(C1::CharRed, C1::BgBlack);
+        let red_on_blue = ColorPair::new(C1::CharRed, C1::BgBlue);
+        assert_eq!(ColorPair::diff(&white_on_black, &white_on_black), vec![]);
+        assert_eq!(
+            ColorPair::diff(&white_on_black, &red_on_black),
+            vec![C1::CharRed]
+        );
+        assert_eq!(
+            ColorPair::diff(&white_on_black, &red_on_blue),
+            vec![C1::CharRed, C1::BgBlue]
+        );
+        assert_eq!(Vec::<C1>::from(red_on_blue), vec![C1::CharRed, C1::BgBlue]);
+    }
+
     #[test]
     pub fn semigraphic_from_bits() {
         assert_eq!(
@@ -612,6 +1407,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn semigraphic_from_bits_exhaustive() {
+        for pixels in 0u8..64 {
+            let bits = [
+                [pixels & 0b000001 != 0, pixels & 0b000010 != 0],
+                [pixels & 0b000100 != 0, pixels & 0b001000 != 0],
+                [pixels & 0b010000 != 0, pixels & 0b100000 != 0],
+            ];
+            // Bit 5 is always set, the last pixel is on bit 6
+            let expected = (pixels & 0b011111) | 0b0100000 | ((pixels & 0b100000) << 1);
+            assert_eq!(G1::from_bits(bits), G1(expected), "pixels {pixels:06b}");
+            assert_eq!(G1(expected).bits(), bits, "pixels {pixels:06b}");
+        }
+    }
+
+    #[test]
+    fn semigraphic_is_set() {
+        let g1 = G1::from_bits([[true, false], [false, false], [false, true]]);
+        assert!(g1.is_set(0, 0));
+        assert!(!g1.is_set(0, 1));
+        assert!(g1.is_set(2, 1));
+        assert!(!g1.is_set(3, 0));
+        assert!(!G1::QUADRANT_BLANK.is_set(1, 1));
+        assert!(G1(0x7F).is_set(1, 1));
+    }
+
     #[test]
     pub fn semigraphic_from_char() {
         assert_eq!(G1::approximate_char('⠉'), Some(G1(0x23)));
@@ -623,4 +1444,274 @@ mod tests {
         assert_eq!(G1::approximate_char('\u{1FB00}'), Some(G1(0x21)));
         assert_eq!(G1::approximate_char('\u{1FB28}'), Some(G1(0x6B)));
     }
+
+    #[test]
+    fn semigraphic_to_unicode() {
+        assert_eq!(G1(0x20).to_unicode(), ' ');
+        assert_eq!(G1(0x7F).to_unicode(), '█');
+        assert_eq!(G1(0x21).to_unicode(), '\u{1FB00}');
+        assert_eq!(G1(0x6B).to_unicode(), '\u{1FB28}');
+        assert_eq!(G1(0x7E).to_unicode(), '\u{1FB3B}');
+        assert_eq!(G1::QUADRANT_LEFT_HALF.to_string(), "▌");
+        for g1 in G1::all() {
+            assert_eq!(G1::approximate_char(g1.to_unicode()), Some(g1), "{g1:?}");
+        }
+    }
+
+    #[test]
+    fn semigraphic_from_quadrants() {
+        assert_eq!(G1::QUADRANT_BLANK, G1(0x20));
+        assert_eq!(G1::QUADRANT_UPPER_HALF, G1(0x2F));
+        assert_eq!(G1::QUADRANT_LEFT_HALF, G1(0x35));
+        assert_eq!(G1::QUADRANT_RIGHT_HALF, G1(0x6A));
+        assert_eq!(G1::QUADRANT_LOWER_HALF, G1(0x70));
+        assert_eq!(G1::from_quadrants(true, true, true, true), G1(0x7F));
+        for mask in 0..16 {
+            let [tl, tr, bl, br] = [0, 1, 2, 3].map(|i| mask & (1 << i) != 0);
+            let bits = [[tl, tr], [tl, tr], [bl, br]];
+            assert_eq!(G1::from_quadrants(tl, tr, bl, br), G1::from_bits(bits));
+        }
+    }
+
+    #[test]
+    fn function_key_ord_hash() {
+        let mut keys = vec![
+            FunctionKey::Unknown(0x40),
+            FunctionKey::Suite,
+            FunctionKey::Envoi,
+        ];
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                FunctionKey::Unknown(0x40),
+                FunctionKey::Envoi,
+                FunctionKey::Suite
+            ]
+        );
+
+        let bindings: std::collections::HashMap<_, _> =
+            [(FunctionKey::Envoi, "send"), (FunctionKey::Retour, "back")].into();
+        assert_eq!(bindings[&FunctionKey::Retour], "back");
+
+        let input = UserInput::FunctionKey(FunctionKey::Guide);
+        assert_eq!(FunctionKey::try_from(input), Ok(FunctionKey::Guide));
+        let input = UserInput::Char('a');
+        assert_eq!(FunctionKey::try_from(input), Err(input));
+    }
+
+    #[test]
+    fn g0_display() {
+        assert_eq!(G0(b'A').to_string(), "A");
+        assert_eq!(G0(0x60).to_string(), "─");
+        assert_eq!(G0(0x10).to_string(), "G0(0x10)");
+        assert_eq!(G0(0x7F).to_string(), "G0(0x7F)");
+        assert!(G0(b'!').is_printable());
+        assert!(!G0(b' ').is_printable());
+        assert!(!G0(0x7F).is_printable());
+    }
+
+    #[test]
+    fn semigraphic_predicates() {
+        assert!(G1(0x7F).is_full_block());
+        assert!(G1(0x20).is_empty());
+        assert!(!G1(0x21).is_empty());
+        assert!(G1::QUADRANT_UPPER_HALF.is_top_half());
+        assert!(G1::QUADRANT_LOWER_HALF.is_bottom_half());
+        assert!(!G1(0x7F).is_top_half());
+        let full = G1::blend(G1::QUADRANT_UPPER_HALF, G1::QUADRANT_LOWER_HALF);
+        assert!(full.is_full_block());
+        assert_eq!(G1::blend(G1(0x20), G1(0x21)), G1(0x21));
+    }
+
+    #[test]
+    fn semigraphic_legacy_computing_block() {
+        for c in '\u{1FB00}'..='\u{1FBFF}' {
+            let expected = match c {
+                // sextants
+                '\u{1FB00}'..='\u{1FB3B}' => true,
+                // upper and right block fractions
+                '\u{1FB82}'..='\u{1FB8B}' => true,
+                // smooth mosaics, one eighth lines, shades, and the rest of the block
+                _ => false,
+            };
+            assert_eq!(G1::approximate_char(c).is_some(), expected, "{c:?}");
+        }
+        assert_eq!(G1::approximate_char('\u{1FB3B}'), Some(G1(0x7E)));
+        assert_eq!(G1::approximate_char('\u{1FB84}'), Some(G1(0x2F)));
+        assert_eq!(G1::approximate_char('\u{1FB88}'), Some(G1(0x6A)));
+    }
+
+    #[test]
+    fn csi_sequences() {
+        assert_eq!(CsiSequence::CursorUp(3).message(), b"\x1B[3A");
+        assert_eq!(CsiSequence::CursorLeft(12).message(), b"\x1B[12D");
+        assert_eq!(CsiSequence::EraseToEndOfScreen.message(), b"\x1B[J");
+        assert_eq!(CsiSequence::EraseFromStartOfLine.message(), b"\x1B[1K");
+        assert_eq!(CsiSequence::EraseLine.message(), b"\x1B[2K");
+        assert_eq!(CsiSequence::DeleteLines(3).message(), b"\x1B[3M");
+    }
+
+    #[test]
+    fn g2_round_trip_all_variants() {
+        for g2 in [
+            G2::Pound,
+            G2::Dollar,
+            G2::Hash,
+            G2::Section,
+            G2::LeftArrow,
+            G2::UpArrow,
+            G2::RightArrow,
+            G2::DownArrow,
+            G2::Degree,
+            G2::PlusMinus,
+            G2::Division,
+            G2::OneQuarter,
+            G2::OneHalf,
+            G2::ThreeQuarters,
+            G2::OeMaj,
+            G2::OeMin,
+            G2::Beta,
+        ] {
+            assert_eq!(G2::try_from(g2.char()), Ok(g2));
+        }
+        assert_eq!(G2::try_from('β'), Ok(G2::Beta));
+        for g2 in [
+            G2::Grave,
+            G2::Acute,
+            G2::Circumflex,
+            G2::Diaeresis,
+            G2::Cedille,
+        ] {
+            assert_eq!(G2::try_from(g2.char()), Err(()));
+            assert_eq!(G2::try_from_standalone(g2.char()), Some(g2));
+        }
+    }
+
+    #[test]
+    fn g0_round_trip_exhaustive() {
+        for b in 0x20..=0x7E {
+            let c = char::from(G0(b));
+            assert_eq!(G0::try_from(c), Ok(G0(b)), "{c:?} (0x{b:02X})");
+        }
+    }
+
+    #[test]
+    fn colored_canvas() {
+        let mut canvas = ColoredCanvas::new(2, 2, C1::CharWhite, C1::BgBlack);
+        for y in 0..3 {
+            canvas.set_pixel(0, y, true);
+        }
+        canvas.set_pixel(3, 5, true);
+        canvas.set_pixel(3, 5, false);
+        canvas.set_pixel(3, 5, true);
+        canvas.set_pixel(4, 6, true);
+        canvas.set_pixel_color(2, 4, C1::CharBlue, C1::BgYellow);
+        assert_eq!(
+            canvas.cells(),
+            &[
+                vec![
+                    (G1::QUADRANT_LEFT_HALF, C1::CharWhite, C1::BgBlack),
+                    (G1::QUADRANT_BLANK, C1::CharWhite, C1::BgBlack),
+                ],
+                vec![
+                    (G1::QUADRANT_BLANK, C1::CharWhite, C1::BgBlack),
+                    (G1(0x60), C1::CharBlue, C1::BgYellow),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_page() {
+        let mut page = SetPosition(39, 24).message();
+        page.extend([0x1B, 0x41, 0x19, 0x42, b'e', 0x19, 0x23, 0x12, 0x43]);
+        page.extend([0x1B, 0x5B, b'1', b'2', b'C', 0x1B, 0x39, 0x7B]);
+        assert_eq!(super::validate_page(&page), Ok(()));
+
+        assert_eq!(
+            super::validate_page(&SetPosition(40, 1).message()),
+            Err(PageError::InvalidPosition(0))
+        );
+        assert_eq!(
+            super::validate_page(b"a\x19\x60"),
+            Err(PageError::InvalidCharacter(1))
+        );
+        assert_eq!(
+            super::validate_page(b"\x1b\x4a"),
+            Err(PageError::InvalidAttribute(0))
+        );
+        assert_eq!(
+            super::validate_page(b"a\x12\x40"),
+            Err(PageError::InvalidRepeat(1))
+        );
+        assert_eq!(
+            super::validate_page(b"\x1b\x3a\x69"),
+            Err(PageError::Truncated(0))
+        );
+        assert_eq!(
+            super::validate_page("é".as_bytes()),
+            Err(PageError::InvalidCharacter(0))
+        );
+    }
+
+    #[test]
+    fn function_key_label() {
+        assert_eq!(FunctionKey::ConnexionFin.label_fr(), "Connexion/Fin");
+        assert_eq!(FunctionKey::Repetition.to_string(), "Répétition");
+        assert_eq!(FunctionKey::from(0x50).to_string(), "FunctionKey(0x50)");
+    }
+
+    #[test]
+    fn user_input_display() {
+        assert_eq!(UserInput::Char('H').to_string(), "'H'");
+        assert_eq!(
+            UserInput::FunctionKey(FunctionKey::Envoi).to_string(),
+            "[Envoi]"
+        );
+        assert_eq!(UserInput::C1(C1::BgBlue).to_string(), "ESC BgBlue");
+        assert_eq!(UserInput::C0(C0::CR).to_string(), "CR");
+    }
+
+    #[test]
+    fn c1_colors() {
+        assert!(C1::CharWhite.is_char_color());
+        assert!(!C1::CharWhite.is_bg_color());
+        assert!(C1::BgBlack.is_bg_color());
+        assert!(!C1::Blink.is_char_color() && !C1::Blink.is_bg_color());
+        assert_eq!(C1::CharRed.corresponding_bg(), Some(C1::BgRed));
+        assert_eq!(C1::BgCyan.corresponding_char(), Some(C1::CharCyan));
+        assert_eq!(C1::BgCyan.corresponding_bg(), Some(C1::BgCyan));
+        assert_eq!(C1::Mask.corresponding_bg(), None);
+    }
+
+    #[test]
+    fn gray_scale() {
+        assert_eq!(GrayScale::from(1), GrayScale::Gray40);
+        assert_eq!(GrayScale::from(12), GrayScale::White);
+        assert_eq!(GrayScale::from_luminance(0.1), GrayScale::Black);
+        assert_eq!(GrayScale::from_luminance(0.42), GrayScale::Gray40);
+        assert_eq!(GrayScale::from_luminance(2.0), GrayScale::White);
+        assert_eq!(GrayScale::Gray40.to_string(), "40%");
+    }
+
+    #[test]
+    fn all_characters() {
+        assert_eq!(G0::all().count(), 95);
+        assert_eq!(G0::all_chars().nth(0x21), Some((G0(0x41), 'A')));
+        assert_eq!(G1::all().count(), 64);
+        assert!(G1::all().all(|g1| g1.0 & 0x20 != 0));
+        assert_eq!(G2::all().count(), 22);
+        assert!(G2::all().all(|g2| !matches!(g2, G2::Unknown(_))));
+    }
+
+    #[test]
+    fn sichar_lossy() {
+        assert_eq!(SIChar::lossy('a'), SIChar::G0(G0(b'a')));
+        assert_eq!(SIChar::lossy('é'), SIChar::G0Diacritic(G0(b'e'), G2::Acute));
+        assert_eq!(SIChar::lossy('☃'), SIChar::G0(G0(b'?')));
+        assert_eq!(SIChar::lossy_with('☃', '_'), Some(SIChar::G0(G0(b'_'))));
+        assert_eq!(SIChar::lossy_with('a', '_'), Some(SIChar::G0(G0(b'a'))));
+        assert_eq!(SIChar::lossy_with('☃', 'é'), None);
+    }
 }
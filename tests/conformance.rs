@@ -0,0 +1,146 @@
+//! Protocol conformance against the sequences of the STUM1B specification
+//!
+//! Each test loads the answer of the minitel, runs a command, then checks the bytes sent.
+
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind, Result};
+
+use minitel::{
+    stum::{
+        protocol::{Baudrate, FunctionMode, RoutingRx, RoutingTx},
+        videotex::SetPosition,
+    },
+    AsyncMinitelRead, AsyncMinitelReadWrite, AsyncMinitelWrite, MinitelMessage,
+};
+
+/// Port answering with pre-loaded responses and recording what is sent
+struct RecordingPort {
+    responses: VecDeque<u8>,
+    sent: Vec<u8>,
+}
+
+impl RecordingPort {
+    fn new(responses: &[u8]) -> Self {
+        Self {
+            responses: responses.iter().copied().collect(),
+            sent: Vec::new(),
+        }
+    }
+}
+
+impl AsyncMinitelRead for RecordingPort {
+    async fn read(&mut self, data: &mut [u8]) -> Result<()> {
+        for byte in data.iter_mut() {
+            *byte = self
+                .responses
+                .pop_front()
+                .ok_or(Error::from(ErrorKind::UnexpectedEof))?;
+        }
+        Ok(())
+    }
+}
+
+impl AsyncMinitelWrite for RecordingPort {
+    async fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.sent.extend_from_slice(data);
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// <https://jbellue.github.io/stum1b/#2-6-6>
+#[tokio::test]
+async fn read_rom() {
+    // SOH, manufacturer, model, version, EOL
+    let mut port = RecordingPort::new(&[0x01, b'C', b'u', b'4', 0x04]);
+    let rom = port.read_rom().await.unwrap();
+    // PRO1 ENQROM
+    assert_eq!(port.sent, vec![0x1B, 0x39, 0x7B]);
+    assert_eq!(rom.manufacturer, b'C');
+    assert_eq!(rom.model, b'u');
+    assert_eq!(rom.version, b'4');
+    assert!(port.responses.is_empty());
+}
+
+/// <https://jbellue.github.io/stum1b/#2-6-5>
+#[tokio::test]
+async fn get_speed() {
+    // PRO2 REP_SPEED, 4800 bauds
+    let mut port = RecordingPort::new(&[0x1B, 0x3A, 0x75, 0b01_110_110]);
+    let speed = port.get_speed().await.unwrap();
+    // PRO1 ENQSPEED
+    assert_eq!(port.sent, vec![0x1B, 0x39, 0x74]);
+    assert!(matches!(speed, Baudrate::B4800));
+}
+
+#[tokio::test]
+async fn get_speed_invalid_code() {
+    let mut port = RecordingPort::new(&[0x1B, 0x3A, 0x75, 0x00]);
+    let err = port.get_speed().await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+/// <https://jbellue.github.io/stum1b/#2-6-3>
+#[tokio::test]
+async fn set_routing() {
+    // PRO3 FROM, modem, keyboard routed
+    let mut port = RecordingPort::new(&[0x1B, 0x3B, 0x63, 0x5A, 0b0010]);
+    port.set_routing(true, RoutingRx::Modem, RoutingTx::Keyboard)
+        .await
+        .unwrap();
+    // PRO3 ON, recepter, emitter
+    assert_eq!(port.sent, vec![0x1B, 0x3B, 0x61, 0x5A, 0x51]);
+
+    let mut port = RecordingPort::new(&[0x1B, 0x3B, 0x63, 0x58, 0b0000]);
+    port.set_routing(false, RoutingRx::Screen, RoutingTx::Keyboard)
+        .await
+        .unwrap();
+    // PRO3 OFF, recepter, emitter
+    assert_eq!(port.sent, vec![0x1B, 0x3B, 0x60, 0x58, 0x51]);
+}
+
+/// <https://jbellue.github.io/stum1b/#2-6-11>
+#[tokio::test]
+async fn set_function_mode() {
+    // PRO2 REP_STATUS, rouleau enabled
+    let mut port = RecordingPort::new(&[0x1B, 0x3A, 0x73, 0x42]);
+    port.set_function_mode(FunctionMode::Rouleau, true)
+        .await
+        .unwrap();
+    // PRO2 START ROULEAU
+    assert_eq!(port.sent, vec![0x1B, 0x3A, 0x69, 0x43]);
+
+    let mut port = RecordingPort::new(&[0x1B, 0x3A, 0x73, 0x40]);
+    port.set_function_mode(FunctionMode::Minuscule, false)
+        .await
+        .unwrap();
+    // PRO2 STOP MINUSCULE
+    assert_eq!(port.sent, vec![0x1B, 0x3A, 0x6A, 0x45]);
+}
+
+/// <https://jbellue.github.io/stum1b/#2-2-1-2-7>
+#[tokio::test]
+async fn get_pos_round_trip() {
+    // The cursor position is answered as it is set
+    let position = SetPosition(3, 5).message();
+    let mut port = RecordingPort::new(&position);
+    port.send(SetPosition(3, 5)).await.unwrap();
+    assert_eq!(port.get_pos().await.unwrap(), (3, 5));
+    // Position, then ESC ENQ_CURSOR
+    assert_eq!(port.sent, [position, vec![0x1B, 0x61]].concat());
+
+    // Status row
+    let mut port = RecordingPort::new(&SetPosition(0, 0).message());
+    assert_eq!(port.get_pos().await.unwrap(), (0, 0));
+}
+
+#[tokio::test]
+async fn unexpected_acknowledgement() {
+    // REP_STATUS instead of REP_SPEED
+    let mut port = RecordingPort::new(&[0x1B, 0x3A, 0x73, 0x40]);
+    let err = port.get_speed().await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}